@@ -0,0 +1,419 @@
+//! A minimal fake of the Discord HTTP API for end-to-end tests without network access.
+//!
+//! Serves the messages and reaction users of a fixture file
+//! (the same format as `--fixture`),
+//! accepts thread creation and message posting,
+//! and records every modifying request as a JSON line
+//! so that the posted toplists can be asserted on.
+//!
+//! Point the bot at it with `api_url = "http://127.0.0.1:<port>"`.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use chrono::Utc;
+use clap::Parser;
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Discord's epoch (2015-01-01) in milliseconds.
+const DISCORD_EPOCH: i64 = 1_420_070_400_000;
+
+const BOT_USER_ID: &str = "100000000000000001";
+const DEFAULT_GUILD_ID: &str = "100000000000000002";
+
+#[derive(Parser, Debug)]
+#[command(about = "Fake Discord API server for testing the bot")]
+struct Args {
+    /// Port to listen on (on 127.0.0.1).
+    #[arg(long, default_value_t = 3000)]
+    port: u16,
+    /// Fixture file with the messages and reaction users to serve.
+    #[arg(long)]
+    fixture: Option<PathBuf>,
+    /// File to append every modifying request to, one JSON object per line.
+    #[arg(long)]
+    record: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct Discord {
+    /// All messages, fixture and posted, in order of their IDs.
+    messages: Vec<Value>,
+    /// Users per message ID and reaction, as in the fixture.
+    reaction_users: HashMap<String, HashMap<String, Vec<Value>>>,
+    threads: Vec<Value>,
+    next_id: u64,
+    record: Option<BufWriter<File>>,
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let args = Args::parse();
+
+    let mut discord = Discord {
+        next_id: ((Utc::now().timestamp_millis() - DISCORD_EPOCH) as u64) << 22,
+        ..Default::default()
+    };
+    if let Some(path) = &args.fixture {
+        let fixture: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        if let Some(messages) = fixture["messages"].as_array() {
+            discord.messages = messages.clone();
+        }
+        if let Some(reaction_users) = fixture.get("reaction_users") {
+            discord.reaction_users = serde_json::from_value(reaction_users.clone())?;
+        }
+        discord.messages.sort_by_key(id_of);
+    }
+    if let Some(path) = &args.record {
+        discord.record = Some(BufWriter::new(File::create(path)?));
+    }
+
+    let server = Server::http(("127.0.0.1", args.port))?;
+    eprintln!(
+        "Mock Discord API listening on http://127.0.0.1:{}",
+        args.port
+    );
+    for mut request in server.incoming_requests() {
+        let mut body = String::new();
+        if let Err(err) = request.as_reader().read_to_string(&mut body) {
+            // E.g. not UTF-8, which Discord rejects as well.
+            eprintln!("{} {} -> 400: {err}", request.method(), request.url());
+            let error =
+                json!({ "code": 50109, "message": "The request body contains invalid JSON." });
+            respond(request, 400, Some(error))?;
+            continue;
+        }
+        let body: Value = serde_json::from_str(&body).unwrap_or(Value::Null);
+        let (status, response) = discord.handle(request.method(), request.url(), &body);
+        eprintln!("{} {} -> {}", request.method(), request.url(), status);
+        respond(request, status, response)?;
+    }
+    Ok(())
+}
+
+fn respond(request: Request, status: u16, body: Option<Value>) -> std::io::Result<()> {
+    let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+    match body {
+        Some(body) => request.respond(
+            Response::from_string(body.to_string())
+                .with_status_code(status)
+                .with_header(content_type),
+        ),
+        None => request.respond(Response::empty(status)),
+    }
+}
+
+impl Discord {
+    fn handle(&mut self, method: &Method, url: &str, body: &Value) -> (u16, Option<Value>) {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let query: HashMap<_, _> = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .collect();
+        let segments: Vec<_> = path
+            .trim_start_matches("/api/v10/")
+            .split('/')
+            .map(percent_decode)
+            .collect();
+        let segments: Vec<_> = segments.iter().map(String::as_str).collect();
+
+        if *method != Method::Get {
+            self.record(method, path, body);
+        }
+
+        let found = match (method, segments.as_slice()) {
+            (Method::Get, ["users", "@me"]) => Some(bot_user()),
+            (Method::Get, ["guilds", guild_id]) => Some(guild(guild_id)),
+            (Method::Get, ["guilds", guild_id, "members", user_id]) => {
+                Some(member(guild_id, user_id))
+            }
+            (Method::Get, ["guilds", _, "emojis"]) => Some(json!([])),
+            (Method::Get, ["guilds", guild_id, "threads", "active"]) => {
+                let threads: Vec<_> = self
+                    .threads
+                    .iter()
+                    .filter(|t| t["guild_id"] == *guild_id)
+                    .cloned()
+                    .collect();
+                Some(json!({ "threads": threads, "members": [] }))
+            }
+            (Method::Get, ["channels", channel_id]) => Some(self.channel(channel_id)),
+            (Method::Patch, ["channels", channel_id]) => self.edit_thread(channel_id, body),
+            (Method::Get, ["channels", _, "threads", "archived", "public"]) => {
+                Some(json!({ "threads": [], "members": [], "has_more": false }))
+            }
+            (Method::Post, ["channels", channel_id, "threads"]) => {
+                Some(self.create_thread(channel_id, body))
+            }
+            (Method::Get, ["channels", channel_id, "messages"]) => {
+                Some(self.messages_after(channel_id, &query))
+            }
+            (Method::Post, ["channels", channel_id, "messages"]) => {
+                Some(self.create_message(channel_id, body))
+            }
+            (Method::Get, ["channels", channel_id, "messages", message_id]) => self
+                .messages
+                .iter()
+                .find(|m| m["channel_id"] == *channel_id && m["id"] == *message_id)
+                .cloned(),
+            (Method::Patch, ["channels", _, "messages", message_id]) => {
+                self.edit_message(message_id, body)
+            }
+            (Method::Delete, ["channels", _, "messages", message_id]) => {
+                self.messages.retain(|m| m["id"] != *message_id);
+                return (204, None);
+            }
+            (Method::Get, ["channels", _, "messages", message_id, "reactions", reaction]) => {
+                Some(self.reaction_users(message_id, reaction, &query))
+            }
+            (Method::Put, ["channels", _, "pins", _])
+            | (Method::Post, ["channels", _, "typing"]) => {
+                return (204, None);
+            }
+            _ => None,
+        };
+
+        match found {
+            Some(value) => (200, Some(value)),
+            None => (
+                404,
+                Some(json!({ "code": 10003, "message": "Unknown Channel or Message" })),
+            ),
+        }
+    }
+
+    fn record(&mut self, method: &Method, path: &str, body: &Value) {
+        if let Some(record) = &mut self.record {
+            let line = json!({ "method": method.to_string(), "path": path, "body": body });
+            writeln!(record, "{line}")
+                .and_then(|()| record.flush())
+                .ok();
+        }
+    }
+
+    fn new_id(&mut self) -> String {
+        self.next_id += 1;
+        self.next_id.to_string()
+    }
+
+    fn guild_of(&self, channel_id: &str) -> String {
+        self.threads
+            .iter()
+            .chain(&self.messages)
+            .find(|v| v["id"] == channel_id || v["channel_id"] == channel_id)
+            .and_then(|v| v["guild_id"].as_str())
+            .unwrap_or(DEFAULT_GUILD_ID)
+            .to_owned()
+    }
+
+    fn channel(&self, channel_id: &str) -> Value {
+        if let Some(thread) = self.threads.iter().find(|t| t["id"] == channel_id) {
+            return thread.clone();
+        }
+        json!({
+            "id": channel_id,
+            "type": 0,
+            "guild_id": self.guild_of(channel_id),
+            "name": format!("channel-{channel_id}"),
+            "position": 0,
+            "permission_overwrites": [],
+            "nsfw": false,
+        })
+    }
+
+    fn create_thread(&mut self, channel_id: &str, body: &Value) -> Value {
+        let thread = json!({
+            "id": self.new_id(),
+            "type": 11,
+            "guild_id": self.guild_of(channel_id),
+            "parent_id": channel_id,
+            "owner_id": BOT_USER_ID,
+            "name": body["name"],
+            "thread_metadata": {
+                "archived": false,
+                "auto_archive_duration": body["auto_archive_duration"].as_u64().unwrap_or(10080),
+                "archive_timestamp": Utc::now().to_rfc3339(),
+                "locked": false,
+            },
+            "permission_overwrites": [],
+        });
+        self.threads.push(thread.clone());
+        thread
+    }
+
+    fn edit_thread(&mut self, channel_id: &str, body: &Value) -> Option<Value> {
+        let thread = self.threads.iter_mut().find(|t| t["id"] == channel_id)?;
+        if let Some(archived) = body.get("archived") {
+            thread["thread_metadata"]["archived"] = archived.clone();
+        }
+        Some(thread.clone())
+    }
+
+    /// Like Discord: the `limit` messages directly after `after`, newest first.
+    fn messages_after(&self, channel_id: &str, query: &HashMap<&str, &str>) -> Value {
+        let after: u64 = query.get("after").and_then(|a| a.parse().ok()).unwrap_or(0);
+        let limit: usize = query
+            .get("limit")
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(50);
+        let mut page: Vec<_> = self
+            .messages
+            .iter()
+            .filter(|m| m["channel_id"] == channel_id && id_of(m) > after)
+            .take(limit)
+            .cloned()
+            .collect();
+        page.reverse();
+        Value::Array(page)
+    }
+
+    fn create_message(&mut self, channel_id: &str, body: &Value) -> Value {
+        let message = json!({
+            "id": self.new_id(),
+            "channel_id": channel_id,
+            "guild_id": self.guild_of(channel_id),
+            "author": bot_user(),
+            "content": body["content"].as_str().unwrap_or_default(),
+            "timestamp": Utc::now().to_rfc3339(),
+            "edited_timestamp": null,
+            "tts": false,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "attachments": [],
+            "embeds": body["embeds"].as_array().cloned().unwrap_or_default(),
+            "pinned": false,
+            "type": 0,
+        });
+        self.messages.push(message.clone());
+        message
+    }
+
+    fn edit_message(&mut self, message_id: &str, body: &Value) -> Option<Value> {
+        let message = self.messages.iter_mut().find(|m| m["id"] == message_id)?;
+        for key in ["content", "embeds"] {
+            if let Some(value) = body.get(key) {
+                message[key] = value.clone();
+            }
+        }
+        message["edited_timestamp"] = json!(Utc::now().to_rfc3339());
+        Some(message.clone())
+    }
+
+    fn reaction_users(
+        &self,
+        message_id: &str,
+        reaction: &str,
+        query: &HashMap<&str, &str>,
+    ) -> Value {
+        let limit: usize = query
+            .get("limit")
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(25);
+        let reactions = self.reaction_users.get(message_id);
+        // Custom emoji are requested as `name:id`, but keyed as `<:name:id>`.
+        let users = reactions.and_then(|reactions| {
+            reactions
+                .get(reaction)
+                .or_else(|| reactions.get(&format!("<:{reaction}>")))
+                .or_else(|| reactions.get(&format!("<a:{reaction}>")))
+        });
+        Value::Array(users.into_iter().flatten().take(limit).cloned().collect())
+    }
+}
+
+fn id_of(value: &Value) -> u64 {
+    value["id"]
+        .as_str()
+        .and_then(|id| id.parse().ok())
+        .unwrap_or_default()
+}
+
+fn bot_user() -> Value {
+    json!({
+        "id": BOT_USER_ID,
+        "username": "toplist-bot",
+        "discriminator": "0",
+        "global_name": null,
+        "avatar": null,
+        "bot": true,
+    })
+}
+
+/// A guild owned by the bot, so that it has every permission.
+fn guild(guild_id: &str) -> Value {
+    json!({
+        "id": guild_id,
+        "name": "Mock Guild",
+        "icon": null,
+        "splash": null,
+        "discovery_splash": null,
+        "owner_id": BOT_USER_ID,
+        "afk_channel_id": null,
+        "afk_timeout": 300,
+        "verification_level": 0,
+        "default_message_notifications": 0,
+        "explicit_content_filter": 0,
+        "roles": [{
+            "id": guild_id,
+            "name": "@everyone",
+            "color": 0,
+            "colors": { "primary_color": 0, "secondary_color": null, "tertiary_color": null },
+            "icon": null,
+            "unicode_emoji": null,
+            "hoist": false,
+            "position": 0,
+            "permissions": "0",
+            "managed": false,
+            "mentionable": false,
+        }],
+        "emojis": [],
+        "features": [],
+        "mfa_level": 0,
+        "system_channel_flags": 0,
+        "premium_tier": 0,
+        "preferred_locale": "en-US",
+        "nsfw_level": 0,
+        "stickers": [],
+        "premium_progress_bar_enabled": false,
+    })
+}
+
+fn member(guild_id: &str, user_id: &str) -> Value {
+    let mut user = bot_user();
+    user["id"] = json!(user_id);
+    json!({
+        "guild_id": guild_id,
+        "user": user,
+        "roles": [],
+        "joined_at": "2015-01-01T00:00:00+00:00",
+        "deaf": false,
+        "mute": false,
+        "flags": 0,
+    })
+}
+
+fn percent_decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
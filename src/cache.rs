@@ -0,0 +1,328 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serenity::async_trait;
+use serenity::model::channel::{Message, ReactionType};
+use serenity::model::id::{ChannelId, MessageId, UserId};
+use serenity::prelude::SerenityError;
+
+use crate::config::Cache;
+use crate::error::BotError;
+use crate::file_utils;
+use crate::source::DiscordSource;
+
+/// Remembers referenced messages and the users of reactions on disk,
+/// so that reruns and backfills don't request them again.
+///
+/// Entries expire after `ttl_hours`.
+/// The users of a reaction are also requested again
+/// when a scanned message shows a different number of reactions than when they were cached.
+/// Pages of messages are never cached, since they are what tells that a reaction changed.
+#[derive(Debug)]
+pub struct CachedSource<S> {
+    inner: S,
+    path: PathBuf,
+    data: Mutex<CacheFile>,
+    /// Reaction counts of the messages scanned during this run.
+    seen_counts: Mutex<HashMap<(MessageId, String), u64>>,
+    hits: AtomicU32,
+    misses: AtomicU32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CacheFile {
+    #[serde(default)]
+    messages: HashMap<MessageId, Cached<Message>>,
+    /// Keyed by the reaction as Discord formats it, like in fixtures.
+    #[serde(default)]
+    reaction_users: HashMap<MessageId, HashMap<String, Cached<ReactionUsers>>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Cached<T> {
+    /// Unix timestamp in seconds.
+    fetched_at: i64,
+    value: T,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ReactionUsers {
+    /// The limit the users were requested with.
+    limit: u8,
+    /// The number of reactions when the users were requested, if known.
+    count: Option<u64>,
+    users: Vec<UserId>,
+}
+
+impl<S: DiscordSource> CachedSource<S> {
+    /// Wrap `inner`, reading the cache file if there is one.
+    ///
+    /// An unreadable cache file is ignored, it will be replaced when saving.
+    pub fn load(inner: S, config: &Cache) -> Self {
+        let mut data = match std::fs::read_to_string(&config.file) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|err| {
+                eprintln!("Ignoring invalid cache {:?}: {err}", config.file);
+                CacheFile::default()
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => CacheFile::default(),
+            Err(err) => {
+                eprintln!("Ignoring unreadable cache {:?}: {err}", config.file);
+                CacheFile::default()
+            }
+        };
+
+        let oldest = now() - i64::from(config.ttl_hours) * 3600;
+        data.messages
+            .retain(|_, cached| cached.fetched_at >= oldest);
+        for reactions in data.reaction_users.values_mut() {
+            reactions.retain(|_, cached| cached.fetched_at >= oldest);
+        }
+        data.reaction_users
+            .retain(|_, reactions| !reactions.is_empty());
+
+        CachedSource {
+            inner,
+            path: config.file.clone(),
+            data: Mutex::new(data),
+            seen_counts: Default::default(),
+            hits: AtomicU32::new(0),
+            misses: AtomicU32::new(0),
+        }
+    }
+
+    /// Write the cache file, replacing it atomically.
+    pub fn save(&self) -> Result<(), BotError> {
+        eprintln!(
+            "Cache: {} hits, {} misses, saving to {:?}",
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+            self.path
+        );
+        let contents = serde_json::to_string(&*self.data.lock().unwrap())
+            .map_err(|err| BotError::Io(err.into()))?;
+        file_utils::write_atomically(&self.path, &contents)
+    }
+
+    fn hit<T>(&self, value: T) -> T {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[async_trait]
+impl<S: DiscordSource> DiscordSource for CachedSource<S> {
+    async fn messages_after(
+        &self,
+        channel_id: ChannelId,
+        after: MessageId,
+        limit: u8,
+    ) -> Result<Vec<Message>, SerenityError> {
+        let msgs = self.inner.messages_after(channel_id, after, limit).await?;
+
+        let mut seen_counts = self.seen_counts.lock().unwrap();
+        let mut data = self.data.lock().unwrap();
+        for msg in &msgs {
+            for reaction in &msg.reactions {
+                seen_counts.insert((msg.id, reaction.reaction_type.to_string()), reaction.count);
+            }
+            if let Some(cached) = data.messages.get_mut(&msg.id) {
+                // Keep referenced messages that were scanned as well up to date.
+                cached.value = msg.clone();
+                cached.fetched_at = now();
+            }
+        }
+        Ok(msgs)
+    }
+
+    async fn message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<Message, SerenityError> {
+        let cached = self
+            .data
+            .lock()
+            .unwrap()
+            .messages
+            .get(&message_id)
+            .filter(|cached| cached.value.channel_id == channel_id)
+            .map(|cached| cached.value.clone());
+        if let Some(message) = cached {
+            return Ok(self.hit(message));
+        }
+
+        self.miss();
+        let message = self.inner.message(channel_id, message_id).await?;
+        self.data.lock().unwrap().messages.insert(
+            message_id,
+            Cached {
+                fetched_at: now(),
+                value: message.clone(),
+            },
+        );
+        Ok(message)
+    }
+
+    async fn reaction_users(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reaction: ReactionType,
+        limit: u8,
+    ) -> Result<Vec<UserId>, SerenityError> {
+        let key = reaction.to_string();
+        let count = self
+            .seen_counts
+            .lock()
+            .unwrap()
+            .get(&(message_id, key.clone()))
+            .copied();
+        let cached = self
+            .data
+            .lock()
+            .unwrap()
+            .reaction_users
+            .get(&message_id)
+            .and_then(|reactions| reactions.get(&key))
+            .map(|cached| &cached.value)
+            .filter(|cached| cached.limit >= limit && (count.is_none() || cached.count == count))
+            .map(|cached| cached.users.iter().take(limit.into()).copied().collect());
+        if let Some(users) = cached {
+            return Ok(self.hit(users));
+        }
+
+        self.miss();
+        let users = self
+            .inner
+            .reaction_users(channel_id, message_id, reaction, limit)
+            .await?;
+        self.data
+            .lock()
+            .unwrap()
+            .reaction_users
+            .entry(message_id)
+            .or_default()
+            .insert(
+                key,
+                Cached {
+                    fetched_at: now(),
+                    value: ReactionUsers {
+                        limit,
+                        count,
+                        users: users.clone(),
+                    },
+                },
+            );
+        Ok(users)
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::testing::{TestSource, CHANNEL_ID};
+
+    /// A message of the fixture with five ⭐ reactions.
+    const MESSAGE_ID: MessageId = MessageId::new(1346452114636800000);
+
+    fn star() -> ReactionType {
+        ReactionType::Unicode("⭐".to_owned())
+    }
+
+    /// A cache of the `week-2025-10` fixture in a fresh file named after `name`.
+    fn cache(name: &str) -> (CachedSource<TestSource>, Cache) {
+        let config = Cache {
+            enabled: true,
+            file: std::env::temp_dir()
+                .join(format!("toplist-cache-{name}-{}.json", std::process::id())),
+            ttl_hours: 1,
+        };
+        std::fs::remove_file(&config.file).ok();
+        let source = CachedSource::load(TestSource::new("week-2025-10.json"), &config);
+        (source, config)
+    }
+
+    /// The number of reaction users requests that reached the fixture.
+    fn requested(source: &CachedSource<TestSource>) -> usize {
+        source.inner.requested.lock().unwrap().len()
+    }
+
+    async fn stars(source: &CachedSource<TestSource>, limit: u8) -> Vec<UserId> {
+        source
+            .reaction_users(CHANNEL_ID, MESSAGE_ID, star(), limit)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_requested_again() {
+        let (source, config) = cache("expiry");
+        stars(&source, 100).await;
+        source.save().unwrap();
+
+        let reloaded = CachedSource::load(TestSource::new("week-2025-10.json"), &config);
+        stars(&reloaded, 100).await;
+        assert_eq!(requested(&reloaded), 0);
+
+        for reactions in source.data.lock().unwrap().reaction_users.values_mut() {
+            for cached in reactions.values_mut() {
+                cached.fetched_at -= 2 * 3600;
+            }
+        }
+        source.save().unwrap();
+        let reloaded = CachedSource::load(TestSource::new("week-2025-10.json"), &config);
+        assert!(reloaded.data.lock().unwrap().reaction_users.is_empty());
+        stars(&reloaded, 100).await;
+        assert_eq!(requested(&reloaded), 1);
+        std::fs::remove_file(&config.file).ok();
+    }
+
+    #[tokio::test]
+    async fn changed_counts_are_requested_again() {
+        let (source, config) = cache("counts");
+        source
+            .messages_after(CHANNEL_ID, MessageId::new(1), 100)
+            .await
+            .unwrap();
+        stars(&source, 100).await;
+        stars(&source, 100).await;
+        assert_eq!(requested(&source), 1);
+
+        // Someone reacted since the users were cached.
+        source
+            .seen_counts
+            .lock()
+            .unwrap()
+            .insert((MESSAGE_ID, star().to_string()), 6);
+        stars(&source, 100).await;
+        assert_eq!(requested(&source), 2);
+        stars(&source, 100).await;
+        assert_eq!(requested(&source), 2);
+        std::fs::remove_file(&config.file).ok();
+    }
+
+    #[tokio::test]
+    async fn only_smaller_limits_are_served_from_the_cache() {
+        let (source, config) = cache("limits");
+        assert_eq!(stars(&source, 3).await.len(), 3);
+        assert_eq!(stars(&source, 2).await.len(), 2);
+        assert_eq!(requested(&source), 1);
+
+        assert_eq!(stars(&source, 100).await.len(), 5);
+        assert_eq!(requested(&source), 2);
+        assert_eq!(stars(&source, 3).await.len(), 3);
+        assert_eq!(requested(&source), 2);
+        std::fs::remove_file(&config.file).ok();
+    }
+}
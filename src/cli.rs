@@ -0,0 +1,571 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serenity::model::id::ChannelId;
+
+use crate::error::BotError;
+use crate::time_utils::{self, Period};
+
+const USAGE: &str = "\
+reaction_toplist_bot [OPTIONS] [WEEK]
+       reaction_toplist_bot [OPTIONS] <COMMAND>";
+
+const AFTER_LONG_HELP: &str = "\
+Weeks are either relative to the current week (`+0`, `-1`)
+or absolute ISO weeks in the format `yyyy-ww` (e.g. `2022-10`).
+Without `--week` or `--from`/`--to`, the current week is used.
+`backfill` builds separate toplists for every week from `--from` to `--to`.
+Months for `best-of` are either relative (`+0`, `-1`) or in the format `yyyy-mm`,
+years either relative or in the format `yyyy`.
+
+While posting, the progress is checkpointed to the state file
+(`state_file` in the configuration), which is removed once everything
+has been posted. After a failed run, `resume` continues where it stopped;
+other commands refuse to post then, unless given `--force`.
+
+The bot token is read from the `DISCORD_TOKEN` environment variable
+or from the file named by `DISCORD_TOKEN_FILE`.
+Settings can be overridden with `TOPLIST_*` environment variables,
+such as `TOPLIST_CHANNEL_ID` (see the README).
+
+Exit codes:
+  0  success
+  1  other error reported by Discord
+  2  invalid configuration, arguments or state file
+  3  missing permissions or invalid token
+  4  channel, thread or message not found
+  5  rate limited
+  6  server or network errors that persisted after retrying
+  7  failure to read or write local files";
+
+/// Discord bot for building toplists for configured emoji reactions
+/// for the given ISO calendar week (Mon-Sun)
+/// and posting them in threads.
+#[derive(Parser, Debug)]
+#[command(version, override_usage = USAGE, after_long_help = AFTER_LONG_HELP)]
+pub struct Cli {
+    /// Path to the configuration file.
+    #[arg(long, global = true, default_value = "./config.toml")]
+    pub config: PathBuf,
+
+    /// Create the threads in this channel instead of `target_channel_id`.
+    #[arg(long, global = true, value_name = "CHANNEL_ID")]
+    pub target_channel: Option<ChannelId>,
+
+    /// Only process the given profile of the configuration. Can be repeated.
+    #[arg(long, global = true, value_name = "NAME")]
+    pub profile: Vec<String>,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+
+    /// The arguments of `run` when no subcommand is given.
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Scan the channel and post the toplists (the default).
+    Run(RunArgs),
+    /// Scan the channel and print the toplists without posting anything.
+    Scan(ScanArgs),
+    /// Scan the channel and write the rendered toplists to a file, to be posted with `post`.
+    Export {
+        #[command(flatten)]
+        scan: ScanArgs,
+        /// The file to write.
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Post toplists previously written by `export`.
+    Post {
+        /// The file written by `export`.
+        input: PathBuf,
+        #[command(flatten)]
+        post: PostArgs,
+    },
+    /// Continue posting from the state file of an interrupted run.
+    Resume(PostArgs),
+    /// Scan the weeks from `--from` to `--to` at once and post separate toplists for each week.
+    Backfill {
+        #[command(flatten)]
+        run: RunArgs,
+        /// Also write the rendered toplists of each week to a file,
+        /// named after this one with the week inserted, e.g. `toplists.2025-W10.json`.
+        #[arg(long, value_name = "FILE")]
+        export: Option<PathBuf>,
+    },
+    /// Post the best of a month or year, from the weekly toplists posted before.
+    BestOf(BestOfArgs),
+    /// Check the configuration for mistakes, also against Discord if a token is set.
+    #[command(alias = "validate-config")]
+    Validate,
+}
+
+#[derive(Args, Debug, Default, PartialEq)]
+pub struct ScanArgs {
+    /// The week to build toplists for.
+    #[arg(value_name = "WEEK", allow_negative_numbers = true, conflicts_with_all = ["week", "from"])]
+    pub week_positional: Option<String>,
+
+    /// The week to build toplists for.
+    #[arg(long, allow_negative_numbers = true, conflicts_with = "from")]
+    pub week: Option<String>,
+
+    /// Build toplists over several weeks, starting with this one.
+    #[arg(
+        long,
+        allow_negative_numbers = true,
+        requires = "to",
+        value_name = "WEEK"
+    )]
+    pub from: Option<String>,
+
+    /// The last week (inclusive) when using `--from`.
+    #[arg(
+        long,
+        allow_negative_numbers = true,
+        requires = "from",
+        value_name = "WEEK"
+    )]
+    pub to: Option<String>,
+
+    #[command(flatten)]
+    pub source: SourceArgs,
+}
+
+#[derive(Args, Debug, Default, PartialEq)]
+pub struct SourceArgs {
+    /// Only process the given toplist, by emoji (name, character or shortcode) or `other`.
+    /// Can be repeated.
+    #[arg(long, value_name = "TOPLIST")]
+    pub only: Vec<String>,
+
+    /// Read the messages from a fixture file instead of Discord,
+    /// without connecting. Only when not posting, i.e. with `scan`, `export` or `--dry-run`.
+    #[arg(long, value_name = "FILE", conflicts_with = "import")]
+    pub fixture: Option<PathBuf>,
+
+    /// Read the messages from DiscordChatExporter JSON exports instead of Discord,
+    /// without connecting. Only when not posting, i.e. with `scan`, `export` or `--dry-run`.
+    /// Can be repeated.
+    #[arg(long, value_name = "FILE")]
+    pub import: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct BestOfArgs {
+    /// The month to build the best-of for.
+    #[arg(
+        long,
+        allow_negative_numbers = true,
+        required_unless_present = "year",
+        conflicts_with = "year"
+    )]
+    pub month: Option<String>,
+
+    /// The year to build the best-of for.
+    #[arg(long, allow_negative_numbers = true)]
+    pub year: Option<String>,
+
+    /// Scan the weeks of the period again instead of using the stored weekly toplists.
+    #[arg(long)]
+    pub rescan: bool,
+
+    #[command(flatten)]
+    pub source: SourceArgs,
+
+    #[command(flatten)]
+    pub post: PostArgs,
+
+    /// Print the messages that would be posted instead of posting them.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Args, Debug, Default, PartialEq)]
+pub struct PostArgs {
+    /// What to do when the bot already created a thread for the same period and emoji.
+    #[arg(long, value_enum, default_value_t)]
+    pub on_existing: OnExisting,
+
+    /// Start posting even if the state file of an interrupted run exists,
+    /// discarding what `resume` would continue.
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Args, Debug, Default, PartialEq)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub scan: ScanArgs,
+
+    #[command(flatten)]
+    pub post: PostArgs,
+
+    /// Print the messages that would be posted instead of posting them.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// What to do when a thread for the same period and emoji already exists.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnExisting {
+    /// Leave the existing thread untouched.
+    #[default]
+    Skip,
+    /// Post only the messages that are missing at the end of the existing thread.
+    Append,
+    /// Edit the previously posted messages in place and delete those no longer needed.
+    Edit,
+    /// Create another thread regardless.
+    Duplicate,
+}
+
+/// The command-line arguments, resolved for the event handler.
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub command: Command,
+    pub period: Period,
+    pub on_existing: OnExisting,
+    /// Overwrite the state file of an interrupted run.
+    pub force: bool,
+    pub dry_run: bool,
+    /// Toplists to restrict processing to; empty for all.
+    pub only: Vec<String>,
+    /// Fixture file to scan instead of Discord.
+    pub fixture: Option<PathBuf>,
+    /// Chat exports to scan instead of Discord.
+    pub import: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Scan the channel and post the toplists.
+    Run,
+    /// Scan the channel and print the toplists.
+    Scan,
+    /// Scan the channel and write the rendered toplists to a file.
+    Export { output: PathBuf },
+    /// Post rendered toplists from a file.
+    Post { input: PathBuf },
+    /// Continue posting from the state file of an interrupted run.
+    Resume,
+    /// Scan several weeks and post separate toplists per week,
+    /// optionally writing them to files as well.
+    Backfill { export: Option<PathBuf> },
+    /// Post the best of the weekly toplists of a month or year,
+    /// either from the history or by scanning the weeks again.
+    BestOf { rescan: bool },
+    /// Check the configuration for mistakes.
+    Validate,
+}
+
+impl Cli {
+    pub fn options(self) -> Result<Options, BotError> {
+        // Subcommands are also recognized after the arguments of the implied `run`,
+        // so that the global options can come first.
+        if self.command.is_some() && self.run != RunArgs::default() {
+            return Err(BotError::Config(
+                "only `--config`, `--profile` and `--target-channel` can come before the subcommand"
+                    .to_owned(),
+            ));
+        }
+        if let Some(CliCommand::BestOf(best_of)) = self.command {
+            return best_of.options();
+        }
+        let (command, scan, post, dry_run) = match self.command {
+            None => (Command::Run, self.run.scan, self.run.post, self.run.dry_run),
+            Some(CliCommand::Run(run)) => (Command::Run, run.scan, run.post, run.dry_run),
+            Some(CliCommand::Scan(scan)) => (Command::Scan, scan, Default::default(), true),
+            Some(CliCommand::Export { scan, output }) => {
+                (Command::Export { output }, scan, Default::default(), true)
+            }
+            Some(CliCommand::Post { input, post }) => {
+                (Command::Post { input }, Default::default(), post, false)
+            }
+            Some(CliCommand::Resume(post)) => (Command::Resume, Default::default(), post, false),
+            Some(CliCommand::BestOf(_)) => unreachable!("handled above"),
+            Some(CliCommand::Backfill { run, export }) => {
+                if run.scan.from.is_none() {
+                    return Err(BotError::Config(
+                        "`backfill` needs the weeks to build toplists for with `--from` and `--to`"
+                            .to_owned(),
+                    ));
+                }
+                (
+                    Command::Backfill { export },
+                    run.scan,
+                    run.post,
+                    run.dry_run,
+                )
+            }
+            Some(CliCommand::Validate) => (
+                Command::Validate,
+                Default::default(),
+                Default::default(),
+                true,
+            ),
+        };
+
+        let scanning = matches!(
+            command,
+            Command::Run | Command::Scan | Command::Export { .. } | Command::Backfill { .. }
+        );
+        if scan.source.is_offline() && !(scanning && dry_run) {
+            return Err(BotError::Config(
+                "`--fixture` and `--import` can only be used when not posting, \
+                 i.e. with `scan`, `export` or `--dry-run`"
+                    .to_owned(),
+            ));
+        }
+
+        Ok(Options {
+            command,
+            period: scan.period()?,
+            on_existing: post.on_existing,
+            force: post.force,
+            dry_run,
+            only: scan.source.only,
+            fixture: scan.source.fixture,
+            import: scan.source.import,
+        })
+    }
+}
+
+impl SourceArgs {
+    fn is_offline(&self) -> bool {
+        self.fixture.is_some() || !self.import.is_empty()
+    }
+}
+
+impl BestOfArgs {
+    fn options(self) -> Result<Options, BotError> {
+        if self.source.is_offline() && !(self.rescan && self.dry_run) {
+            return Err(BotError::Config(
+                "`--fixture` and `--import` can only be used with `best-of --rescan --dry-run`"
+                    .to_owned(),
+            ));
+        }
+        let invalid = |err| BotError::Config(format!("invalid period: {err}"));
+        let mut period = match (&self.month, &self.year) {
+            (Some(month), _) => time_utils::parse_month(month).map_err(invalid)?,
+            (_, Some(year)) => time_utils::parse_year(year).map_err(invalid)?,
+            (None, None) => unreachable!("required by clap"),
+        };
+        check_start(&period)?;
+        period.label = format!("Best of {}", period.label);
+
+        Ok(Options {
+            command: Command::BestOf {
+                rescan: self.rescan,
+            },
+            period,
+            on_existing: self.post.on_existing,
+            force: self.post.force,
+            dry_run: self.dry_run,
+            only: self.source.only,
+            fixture: self.source.fixture,
+            import: self.source.import,
+        })
+    }
+}
+
+impl ScanArgs {
+    fn period(&self) -> Result<Period, BotError> {
+        let parse = |week: Option<&str>| {
+            time_utils::parse_iso_week(week)
+                .map_err(|err| BotError::Config(format!("invalid week: {err}")))
+        };
+        let period = match (&self.from, &self.to) {
+            (Some(from), Some(to)) => {
+                Period::weeks(parse(Some(from))?, parse(Some(to))?).map_err(BotError::Config)?
+            }
+            _ => {
+                let week = self.week.as_deref().or(self.week_positional.as_deref());
+                Period::week(parse(week)?)
+            }
+        };
+        check_start(&period)?;
+        Ok(period)
+    }
+}
+
+/// Reject periods starting before Discord's epoch,
+/// before which there are no messages and no message IDs to page with.
+fn check_start(period: &Period) -> Result<(), BotError> {
+    if period.start < time_utils::discord_epoch() {
+        return Err(BotError::Config(format!(
+            "{} starts before Discord's epoch ({})",
+            period.label,
+            time_utils::discord_epoch().date_naive()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, BotError> {
+        let cli = Cli::try_parse_from(["reaction_toplist_bot"].iter().chain(args))
+            .map_err(|err| BotError::Config(err.to_string()))?;
+        assert_eq!(cli.config, PathBuf::from("other.toml"));
+        cli.options()
+    }
+
+    #[test]
+    fn run_is_implied() {
+        let options = parse(&["--config", "other.toml", "2025-10", "--dry-run"]).unwrap();
+        assert_eq!(options.command, Command::Run);
+        assert_eq!(options.period.label, "2025-W10");
+        assert!(options.dry_run);
+
+        let options = parse(&["2025-10", "--config", "other.toml"]).unwrap();
+        assert_eq!(options.command, Command::Run);
+        assert!(!options.dry_run);
+    }
+
+    #[test]
+    fn run() {
+        let options = parse(&[
+            "--config",
+            "other.toml",
+            "run",
+            "--week",
+            "2025-10",
+            "--on-existing",
+            "edit",
+        ])
+        .unwrap();
+        assert_eq!(options.command, Command::Run);
+        assert_eq!(options.period.label, "2025-W10");
+        assert_eq!(options.on_existing, OnExisting::Edit);
+    }
+
+    #[test]
+    fn scan() {
+        let options = parse(&["--config", "other.toml", "scan", "2025-10"]).unwrap();
+        assert_eq!(options.command, Command::Scan);
+        assert_eq!(options.period.label, "2025-W10");
+        assert!(options.dry_run);
+    }
+
+    #[test]
+    fn export() {
+        let options = parse(&[
+            "--config",
+            "other.toml",
+            "export",
+            "2025-10",
+            "--output",
+            "toplists.json",
+        ])
+        .unwrap();
+        assert_eq!(
+            options.command,
+            Command::Export {
+                output: PathBuf::from("toplists.json")
+            }
+        );
+    }
+
+    #[test]
+    fn post() {
+        let options = parse(&["--config", "other.toml", "post", "toplists.json"]).unwrap();
+        assert_eq!(
+            options.command,
+            Command::Post {
+                input: PathBuf::from("toplists.json")
+            }
+        );
+        assert!(!options.dry_run);
+    }
+
+    #[test]
+    fn resume() {
+        let options = parse(&["--config", "other.toml", "--profile", "a", "resume"]).unwrap();
+        assert_eq!(options.command, Command::Resume);
+    }
+
+    #[test]
+    fn backfill() {
+        let options = parse(&[
+            "--config",
+            "other.toml",
+            "backfill",
+            "--from",
+            "2025-10",
+            "--to",
+            "2025-12",
+        ])
+        .unwrap();
+        assert_eq!(options.command, Command::Backfill { export: None });
+        assert_eq!(options.period.split_weeks().len(), 3);
+
+        assert!(parse(&["--config", "other.toml", "backfill", "2025-10"]).is_err());
+    }
+
+    #[test]
+    fn best_of() {
+        let options = parse(&[
+            "--config",
+            "other.toml",
+            "--target-channel",
+            "1",
+            "best-of",
+            "--month",
+            "2025-03",
+        ])
+        .unwrap();
+        assert_eq!(options.command, Command::BestOf { rescan: false });
+        assert_eq!(options.period.label, "Best of March 2025");
+    }
+
+    #[test]
+    fn validate() {
+        let options = parse(&["--config", "other.toml", "validate"]).unwrap();
+        assert_eq!(options.command, Command::Validate);
+        let options = parse(&["validate-config", "--config", "other.toml"]).unwrap();
+        assert_eq!(options.command, Command::Validate);
+    }
+
+    #[test]
+    fn run_arguments_before_subcommand() {
+        assert!(parse(&["--config", "other.toml", "--dry-run", "scan"]).is_err());
+        assert!(parse(&["--config", "other.toml", "2025-10", "validate"]).is_err());
+    }
+
+    #[test]
+    fn periods_before_discord_are_rejected() {
+        let before = |args: &[&str]| match parse(args) {
+            Err(BotError::Config(msg)) => msg.contains("before Discord's epoch"),
+            _ => false,
+        };
+        assert!(before(&["--config", "other.toml", "scan", "2014-10"]));
+        // The first ISO week of 2015 starts on 29 December 2014.
+        assert!(before(&["--config", "other.toml", "scan", "2015-01"]));
+        assert!(before(&[
+            "--config",
+            "other.toml",
+            "backfill",
+            "--from",
+            "2014-52",
+            "--to",
+            "2015-03"
+        ]));
+        assert!(before(&[
+            "--config",
+            "other.toml",
+            "best-of",
+            "--year",
+            "2014",
+            "--rescan",
+            "--dry-run"
+        ]));
+        assert!(parse(&["--config", "other.toml", "scan", "2015-02"]).is_ok());
+        assert!(parse(&["--config", "other.toml", "best-of", "--month", "2015-01"]).is_ok());
+    }
+}
@@ -1,8 +1,13 @@
-use std::{error::Error, path::Path};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 use serde::Deserialize;
 use serenity::model::id::{ChannelId, EmojiId};
 
+use crate::error::BotError;
+
 fn default_max() -> usize {
     15
 }
@@ -11,8 +16,58 @@ fn default_per_reaction_limit() -> u8 {
     50
 }
 
+fn default_max_attempts() -> u32 {
+    4
+}
+
+fn default_retry_budget() -> u32 {
+    50
+}
+
+fn default_base_delay_ms() -> u64 {
+    1000
+}
+
+fn default_max_delay_ms() -> u64 {
+    30_000
+}
+
+fn default_history_file() -> PathBuf {
+    PathBuf::from("./toplist-history.json")
+}
+
+fn default_eligible_rank() -> usize {
+    3
+}
+
+fn default_cache_file() -> PathBuf {
+    PathBuf::from("./toplist-cache.json")
+}
+
+fn default_ttl_hours() -> u32 {
+    24 * 7
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+fn default_slice_hours() -> u32 {
+    24
+}
+
+/// Name of the profile when the configuration has no `[profile.<name>]` tables.
+pub const DEFAULT_PROFILE: &str = "default";
+
+fn default_state_file() -> PathBuf {
+    PathBuf::from("./toplist-state.json")
+}
+
+/// The configuration of one profile.
 #[derive(Deserialize, Debug)]
 pub struct Config {
+    #[serde(skip)]
+    pub name: String,
     pub channel_id: ChannelId,
     #[serde(default)]
     pub target_channel_id: Option<ChannelId>,
@@ -20,17 +75,329 @@ pub struct Config {
     pub per_reaction_limit: u8,
     pub toplist: Vec<Toplist>,
     pub other: Other,
+    #[serde(default)]
+    pub summary: Summary,
+    #[serde(default = "default_state_file")]
+    pub state_file: PathBuf,
+    /// Where the posted weekly toplists are recorded for best-of toplists.
+    #[serde(default = "default_history_file")]
+    pub history_file: PathBuf,
+    #[serde(default)]
+    pub best_of: BestOf,
+    #[serde(default)]
+    pub retry: Retry,
+    #[serde(default)]
+    pub matching: Matching,
+    #[serde(default)]
+    pub scan: Scan,
+    #[serde(default)]
+    pub cache: Cache,
+    /// Base URL to send Discord API requests to instead of `https://discord.com`,
+    /// e.g. a local mock server.
+    /// The gateway is not used then, only HTTP requests.
+    #[serde(default)]
+    pub api_url: Option<String>,
+    /// Read from `DISCORD_TOKEN` or the file named by `DISCORD_TOKEN_FILE`.
+    #[serde(skip)]
+    pub token: Option<Token>,
+}
+
+/// The bot token, kept out of debug output.
+#[derive(Clone)]
+pub struct Token(String);
+
+impl Token {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("<redacted>")
+    }
 }
 
 impl Config {
-    pub fn from_path(path: &Path) -> Result<Config, Box<dyn Error>> {
-        let contents = std::fs::read_to_string(path)?;
-        Ok(toml::from_str(&contents)?)
+    /// Read the configuration file, returning one `Config` per profile.
+    ///
+    /// Without `[profile.<name>]` tables, the whole file is a single profile.
+    /// Otherwise, the top-level settings are shared by all profiles
+    /// and each profile's table overrides them, merging nested tables.
+    pub fn from_path(path: &Path) -> Result<Vec<Config>, BotError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| BotError::Config(format!("unable to read {}: {err}", path.display())))?;
+        let invalid =
+            |err: toml::de::Error| BotError::Config(format!("invalid {}: {err}", path.display()));
+        let mut shared: toml::Table = toml::from_str(&contents).map_err(invalid)?;
+
+        let mut configs = Vec::new();
+        match shared.remove("profile") {
+            None => {
+                let mut config: Config = toml::from_str(&contents).map_err(invalid)?;
+                config.name = DEFAULT_PROFILE.to_owned();
+                config.apply_env()?;
+                configs.push(config);
+            }
+            Some(toml::Value::Table(profiles)) => {
+                for (name, profile) in profiles {
+                    let toml::Value::Table(profile) = profile else {
+                        return Err(BotError::Config(format!("profile `{name}` is not a table")));
+                    };
+                    // All profiles are processed on the same connection.
+                    if profile.contains_key("api_url") {
+                        return Err(BotError::Config(format!(
+                            "profile `{name}` sets `api_url`, which can only be set at the top level"
+                        )));
+                    }
+                    // Overrides from the environment apply to every profile,
+                    // so they are only the base of each profile's own file.
+                    let own_state_file = profile.contains_key("state_file")
+                        && std::env::var_os("TOPLIST_STATE_FILE").is_none();
+                    let own_history_file = profile.contains_key("history_file")
+                        && std::env::var_os("TOPLIST_HISTORY_FILE").is_none();
+                    let mut table = shared.clone();
+                    merge_tables(&mut table, profile);
+                    let mut config: Config =
+                        toml::Value::Table(table).try_into().map_err(|err| {
+                            BotError::Config(format!("invalid profile `{name}`: {err}"))
+                        })?;
+                    config.apply_env()?;
+                    // Keep the progress and history of each profile apart.
+                    if !own_state_file {
+                        config.state_file = profile_path(&config.state_file, &name);
+                    }
+                    if !own_history_file {
+                        config.history_file = profile_path(&config.history_file, &name);
+                    }
+                    config.name = name;
+                    configs.push(config);
+                }
+            }
+            Some(_) => return Err(BotError::Config("`profile` must be a table".to_owned())),
+        }
+        if configs.is_empty() {
+            return Err(BotError::Config("no profiles configured".to_owned()));
+        }
+
+        let token = read_token()?;
+        for config in &mut configs {
+            config.token = token.clone();
+            config.resolve_shortcodes()?;
+        }
+        Ok(configs)
+    }
+
+    /// Override settings from `TOPLIST_*` environment variables.
+    fn apply_env(&mut self) -> Result<(), BotError> {
+        env_override("TOPLIST_CHANNEL_ID", &mut self.channel_id)?;
+        if let Some(channel_id) = env_value("TOPLIST_TARGET_CHANNEL_ID")? {
+            self.target_channel_id = Some(channel_id);
+        }
+        env_override("TOPLIST_PER_REACTION_LIMIT", &mut self.per_reaction_limit)?;
+        env_override("TOPLIST_STATE_FILE", &mut self.state_file)?;
+        env_override("TOPLIST_HISTORY_FILE", &mut self.history_file)?;
+        env_override(
+            "TOPLIST_BEST_OF_ELIGIBLE_RANK",
+            &mut self.best_of.eligible_rank,
+        )?;
+        env_override("TOPLIST_OTHER_ENABLED", &mut self.other.enabled)?;
+        env_override("TOPLIST_OTHER_MAX", &mut self.other.max)?;
+        env_override("TOPLIST_SUMMARY_ENABLED", &mut self.summary.enabled)?;
+        env_override("TOPLIST_SUMMARY_PIN", &mut self.summary.pin)?;
+        env_override("TOPLIST_RETRY_MAX_ATTEMPTS", &mut self.retry.max_attempts)?;
+        env_override("TOPLIST_RETRY_BUDGET", &mut self.retry.budget)?;
+        env_override("TOPLIST_SCAN_CONCURRENCY", &mut self.scan.concurrency)?;
+        env_override("TOPLIST_SCAN_SLICE_HOURS", &mut self.scan.slice_hours)?;
+        env_override("TOPLIST_CACHE_ENABLED", &mut self.cache.enabled)?;
+        env_override("TOPLIST_CACHE_FILE", &mut self.cache.file)?;
+        env_override("TOPLIST_CACHE_TTL_HOURS", &mut self.cache.ttl_hours)?;
+        env_override(
+            "TOPLIST_MATCHING_MERGE_SKIN_TONES",
+            &mut self.matching.merge_skin_tones,
+        )?;
+        if let Some(api_url) = env_value("TOPLIST_API_URL")? {
+            self.api_url = Some(api_url);
+        }
+        Ok(())
+    }
+
+    fn emojis_mut(&mut self) -> impl Iterator<Item = &mut Emoji> {
+        self.toplist
+            .iter_mut()
+            .map(|entry| &mut entry.emoji)
+            .chain(self.other.ignore.iter_mut())
+    }
+
+    /// Replace shortcodes such as ":upside_down_face:" with the emoji they stand for.
+    fn resolve_shortcodes(&mut self) -> Result<(), BotError> {
+        for emoji in self.emojis_mut() {
+            let Emoji::Unicode { string } = emoji else {
+                continue;
+            };
+            let Some(shortcode) = string.strip_prefix(':').and_then(|s| s.strip_suffix(':')) else {
+                continue;
+            };
+            let found = emojis::get_by_shortcode(shortcode)
+                .ok_or_else(|| BotError::Config(format!("unknown emoji shortcode `{string}`")))?;
+            *string = found.as_str().to_owned();
+        }
+        Ok(())
+    }
+
+    pub fn has_named_emojis(&self) -> bool {
+        self.toplist
+            .iter()
+            .map(|entry| &entry.emoji)
+            .chain(&self.other.ignore)
+            .any(|emoji| matches!(emoji, Emoji::Named { .. }))
+    }
+
+    /// Resolve custom emoji configured by name only
+    /// against the `available` ones, e.g. the guild's emoji, by name.
+    ///
+    /// Returns the names that could not be found.
+    pub fn resolve_named_emojis(&mut self, available: &HashMap<String, EmojiId>) -> Vec<String> {
+        let mut unresolved = Vec::new();
+        for emoji in self.emojis_mut() {
+            let Emoji::Named { name } = emoji else {
+                continue;
+            };
+            match available.get(name) {
+                Some(&id) => {
+                    *emoji = Emoji::Custom {
+                        name: name.clone(),
+                        id,
+                    }
+                }
+                None => unresolved.push(name.clone()),
+            }
+        }
+        unresolved
     }
 
     pub fn target_channel_id(&self) -> ChannelId {
         self.target_channel_id.unwrap_or(self.channel_id)
     }
+
+    /// The channel overrides of every enabled toplist, including 'Other'.
+    pub fn toplist_channels(&self) -> impl Iterator<Item = &Channels> {
+        self.toplist
+            .iter()
+            .map(|entry| &entry.channels)
+            .chain(self.other.enabled.then_some(&self.other.channels))
+    }
+
+    /// The channel to scan for a toplist.
+    pub fn source_channel(&self, channels: &Channels) -> ChannelId {
+        channels.channel_id.unwrap_or(self.channel_id)
+    }
+
+    /// The channel to create the thread of a toplist in.
+    ///
+    /// A toplist scanning its own channel posts there
+    /// unless a `target_channel_id` is configured.
+    pub fn target_channel(&self, channels: &Channels) -> ChannelId {
+        channels
+            .target_channel_id
+            .or(self.target_channel_id)
+            .unwrap_or_else(|| self.source_channel(channels))
+    }
+
+    /// Create all threads in `channel_id`, ignoring the configured target channels.
+    pub fn override_target_channel(&mut self, channel_id: ChannelId) {
+        self.target_channel_id = Some(channel_id);
+        for entry in &mut self.toplist {
+            entry.channels.target_channel_id = None;
+        }
+        self.other.channels.target_channel_id = None;
+    }
+}
+
+/// Channels of a single toplist, overriding the top-level settings.
+#[derive(Deserialize, Debug, Default)]
+pub struct Channels {
+    #[serde(default)]
+    pub channel_id: Option<ChannelId>,
+    #[serde(default)]
+    pub target_channel_id: Option<ChannelId>,
+}
+
+/// `path` with the profile name inserted before the extension,
+/// e.g. "toplist-state.json" becomes "toplist-state.<name>.json".
+/// Override the shared settings with those of a profile.
+///
+/// Tables such as `scan` are merged setting by setting,
+/// anything else, including arrays such as `toplist`, is replaced as a whole.
+fn merge_tables(shared: &mut toml::Table, profile: toml::Table) {
+    for (key, value) in profile {
+        match (shared.get_mut(&key), value) {
+            (Some(toml::Value::Table(shared)), toml::Value::Table(value)) => {
+                merge_tables(shared, value)
+            }
+            (_, value) => {
+                shared.insert(key, value);
+            }
+        }
+    }
+}
+
+pub fn profile_path(path: &Path, name: &str) -> PathBuf {
+    match path.extension() {
+        Some(ext) => path.with_extension(format!("{name}.{}", ext.to_string_lossy())),
+        None => path.with_extension(name),
+    }
+}
+
+fn env_value<T: FromStr>(name: &str) -> Result<Option<T>, BotError>
+where
+    T::Err: fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|err| BotError::Config(format!("invalid `{name}`: {err}"))),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(err) => Err(BotError::Config(format!("invalid `{name}`: {err}"))),
+    }
+}
+
+fn env_override<T: FromStr>(name: &str, value: &mut T) -> Result<(), BotError>
+where
+    T::Err: fmt::Display,
+{
+    if let Some(new) = env_value(name)? {
+        *value = new;
+    }
+    Ok(())
+}
+
+/// Read the token from `DISCORD_TOKEN`
+/// or from the file named by `DISCORD_TOKEN_FILE` (e.g. a container secret).
+fn read_token() -> Result<Option<Token>, BotError> {
+    let token = std::env::var("DISCORD_TOKEN").ok();
+    let token_file = std::env::var_os("DISCORD_TOKEN_FILE");
+    let token = match (token, token_file) {
+        (Some(_), Some(_)) => {
+            return Err(BotError::Config(
+                "only one of `DISCORD_TOKEN` and `DISCORD_TOKEN_FILE` may be set".to_owned(),
+            ))
+        }
+        (Some(token), None) => token,
+        (None, Some(path)) => std::fs::read_to_string(&path).map_err(|err| {
+            BotError::Config(format!(
+                "unable to read `DISCORD_TOKEN_FILE` {}: {err}",
+                Path::new(&path).display()
+            ))
+        })?,
+        (None, None) => return Ok(None),
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(BotError::Config("the token is empty".to_owned()));
+    }
+    Ok(Some(Token(token.to_owned())))
 }
 
 #[derive(Deserialize, Debug)]
@@ -38,6 +405,10 @@ pub struct Toplist {
     #[serde(default = "default_max")]
     pub max: usize,
     pub emoji: Emoji,
+    #[serde(default)]
+    pub layout: Layout,
+    #[serde(flatten)]
+    pub channels: Channels,
 }
 
 #[derive(Deserialize, Debug)]
@@ -48,11 +419,313 @@ pub struct Other {
     pub max: usize,
     #[serde(default)]
     pub ignore: Vec<Emoji>,
+    #[serde(default)]
+    pub layout: Layout,
+    #[serde(flatten)]
+    pub channels: Channels,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Summary {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub pin: bool,
+}
+
+/// Retry behavior for transient Discord errors (server errors and network failures).
+#[derive(Deserialize, Debug, Clone)]
+pub struct Retry {
+    /// Attempts per request, including the first one.
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Total number of retries allowed during a run.
+    #[serde(default = "default_retry_budget")]
+    pub budget: u32,
+    #[serde(default = "default_base_delay_ms")]
+    pub base_delay_ms: u64,
+    #[serde(default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Retry {
+            max_attempts: default_max_attempts(),
+            budget: default_retry_budget(),
+            base_delay_ms: default_base_delay_ms(),
+            max_delay_ms: default_max_delay_ms(),
+        }
+    }
+}
+
+/// How the messages of a channel are fetched.
+///
+/// The period is split into slices of `slice_hours`
+/// whose messages are fetched concurrently.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Scan {
+    /// Requests in flight at the same time,
+    /// i.e. slices fetched while scanning
+    /// and messages whose reacting users are counted for 'Other'.
+    #[serde(default = "default_concurrency")]
+    pub concurrency: usize,
+    #[serde(default = "default_slice_hours")]
+    pub slice_hours: u32,
+}
+
+impl Default for Scan {
+    fn default() -> Self {
+        Scan {
+            concurrency: default_concurrency(),
+            slice_hours: default_slice_hours(),
+        }
+    }
+}
+
+/// How best-of toplists are built from the weekly toplists.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BestOf {
+    /// Only entries with this rank or better in their week are eligible.
+    #[serde(default = "default_eligible_rank")]
+    pub eligible_rank: usize,
+}
+
+impl Default for BestOf {
+    fn default() -> Self {
+        BestOf {
+            eligible_rank: default_eligible_rank(),
+        }
+    }
+}
+
+/// The on-disk cache of referenced messages and the users of reactions.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Cache {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_cache_file")]
+    pub file: PathBuf,
+    /// How long cached entries are used before requesting them again.
+    #[serde(default = "default_ttl_hours")]
+    pub ttl_hours: u32,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Cache {
+            enabled: false,
+            file: default_cache_file(),
+            ttl_hours: default_ttl_hours(),
+        }
+    }
+}
+
+/// How reactions are matched against the configured unicode emoji.
+///
+/// Variation selectors (e.g. the U+FE0F in "♻\u{fe0f}") are always ignored.
+#[derive(Deserialize, Debug, Default)]
+pub struct Matching {
+    /// Treat all skin tone variants of an emoji (e.g. 👍🏽) as the emoji itself (👍).
+    #[serde(default)]
+    pub merge_skin_tones: bool,
+}
+
+impl Matching {
+    pub fn normalize_unicode(&self, string: &str) -> String {
+        string
+            .chars()
+            .filter(|c| !matches!(c, '\u{fe0e}' | '\u{fe0f}'))
+            .filter(|c| !(self.merge_skin_tones && SKIN_TONE_MODIFIERS.contains(c)))
+            .collect()
+    }
+
+    /// The emoji as it is compared to reactions.
+    pub fn normalize(&self, emoji: &Emoji) -> Emoji {
+        match emoji {
+            Emoji::Custom { .. } | Emoji::Named { .. } => emoji.clone(),
+            Emoji::Unicode { string } => Emoji::Unicode {
+                string: self.normalize_unicode(string),
+            },
+        }
+    }
+}
+
+const SKIN_TONE_MODIFIERS: std::ops::RangeInclusive<char> = '\u{1f3fb}'..='\u{1f3ff}';
+
+/// How the entries of a toplist are posted into its thread.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    /// Three messages per entry: rank, content and author with reactions.
+    #[default]
+    Classic,
+    /// One embed per entry, packed into as few messages as Discord allows.
+    Compact,
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 #[serde(untagged)]
 pub enum Emoji {
-    Custom { name: String, id: EmojiId },
-    Unicode { string: String },
+    Custom {
+        name: String,
+        id: EmojiId,
+    },
+    /// Either the emoji itself or a shortcode like ":upside_down_face:".
+    Unicode {
+        string: String,
+    },
+    /// A custom emoji of the guild, looked up by name at startup.
+    Named {
+        name: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variation_selectors_are_ignored() {
+        let matching = Matching::default();
+        assert_eq!(matching.normalize_unicode("♻\u{fe0f}"), "♻");
+        assert_eq!(matching.normalize_unicode("♻\u{fe0e}"), "♻");
+        assert_eq!(matching.normalize_unicode("♻"), "♻");
+        assert_eq!(
+            matching.normalize(&Emoji::Unicode {
+                string: "♻\u{fe0f}".to_owned()
+            }),
+            Emoji::Unicode {
+                string: "♻".to_owned()
+            }
+        );
+    }
+
+    #[test]
+    fn skin_tones_are_merged_if_configured() {
+        let separate = Matching::default();
+        assert_eq!(separate.normalize_unicode("👍🏽"), "👍🏽");
+        assert_ne!(
+            separate.normalize_unicode("👍🏽"),
+            separate.normalize_unicode("👍")
+        );
+
+        let merged = Matching {
+            merge_skin_tones: true,
+        };
+        for tone in ["👍🏻", "👍🏼", "👍🏽", "👍🏾", "👍🏿"] {
+            assert_eq!(merged.normalize_unicode(tone), "👍");
+        }
+        // Both a variation selector and a skin tone.
+        assert_eq!(merged.normalize_unicode("✌\u{fe0f}🏽"), "✌");
+    }
+
+    #[test]
+    fn named_emojis_are_resolved() {
+        let mut config: Config = toml::from_str(
+            r#"
+            channel_id = 1
+            toplist = [{ emoji.name = "yes" }, { emoji.name = "pog" }]
+            other = { ignore = [{ name = "no" }] }
+            "#,
+        )
+        .unwrap();
+        assert!(config.has_named_emojis());
+
+        let available = HashMap::from([
+            ("yes".to_owned(), EmojiId::new(11)),
+            ("no".to_owned(), EmojiId::new(12)),
+        ]);
+        assert_eq!(config.resolve_named_emojis(&available), ["pog"]);
+        assert_eq!(
+            config.toplist[0].emoji,
+            Emoji::Custom {
+                name: "yes".to_owned(),
+                id: EmojiId::new(11)
+            }
+        );
+        assert_eq!(
+            config.other.ignore,
+            [Emoji::Custom {
+                name: "no".to_owned(),
+                id: EmojiId::new(12)
+            }]
+        );
+        assert!(config.has_named_emojis());
+    }
+
+    #[test]
+    fn env_files_are_kept_apart_per_profile() {
+        let path = std::env::temp_dir().join("toplist-profiles-env.toml");
+        std::fs::write(
+            &path,
+            r#"
+            channel_id = 1
+            toplist = []
+            other = {}
+            [profile.a]
+            state_file = "a-state.json"
+            [profile.b]
+            "#,
+        )
+        .unwrap();
+        // No other test reads these variables.
+        std::env::set_var("TOPLIST_STATE_FILE", "/data/state.json");
+        let configs = Config::from_path(&path);
+        std::env::remove_var("TOPLIST_STATE_FILE");
+        std::fs::remove_file(&path).unwrap();
+
+        let files: Vec<_> = configs
+            .unwrap()
+            .into_iter()
+            .map(|config| (config.state_file, config.history_file))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                (
+                    PathBuf::from("/data/state.a.json"),
+                    PathBuf::from("./toplist-history.a.json")
+                ),
+                (
+                    PathBuf::from("/data/state.b.json"),
+                    PathBuf::from("./toplist-history.b.json")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn profiles_merge_tables() {
+        let mut shared: toml::Table = toml::from_str(
+            r#"
+            channel_id = 1
+            toplist = [{ emoji.string = "⭐" }, { emoji.string = "🙃" }]
+            scan = { concurrency = 8, slice_hours = 12 }
+            "#,
+        )
+        .unwrap();
+        let profile: toml::Table = toml::from_str(
+            r#"
+            channel_id = 2
+            toplist = [{ emoji.string = "♻" }]
+            scan = { slice_hours = 6 }
+            other = { enabled = true }
+            "#,
+        )
+        .unwrap();
+        merge_tables(&mut shared, profile);
+        let config: Config = toml::Value::Table(shared).try_into().unwrap();
+
+        assert_eq!(config.channel_id, ChannelId::new(2));
+        let emojis: Vec<_> = config.toplist.iter().map(|entry| &entry.emoji).collect();
+        assert_eq!(
+            emojis,
+            [&Emoji::Unicode {
+                string: "♻".to_owned()
+            }]
+        );
+        assert_eq!((config.scan.concurrency, config.scan.slice_hours), (8, 6));
+        assert!(config.other.enabled);
+    }
 }
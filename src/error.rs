@@ -0,0 +1,94 @@
+use std::fmt;
+use std::process::ExitCode;
+
+use serenity::http::{HttpError, StatusCode};
+use serenity::prelude::SerenityError;
+
+// https://discord.com/developers/docs/topics/opcodes-and-status-codes#json-json-error-codes
+const MISSING_ACCESS: isize = 50001;
+const MISSING_PERMISSIONS: isize = 50013;
+
+/// Everything that can make a run fail,
+/// categorized so that the process exit code tells monitoring what went wrong.
+#[derive(Debug)]
+pub enum BotError {
+    /// Invalid configuration, command-line arguments or state file.
+    Config(String),
+    /// Missing permissions or an invalid token.
+    Permission(String),
+    /// Rate limited beyond what the ratelimiter could absorb.
+    RateLimit(Box<SerenityError>),
+    /// Server errors and network failures that persisted after retrying.
+    Network(Box<SerenityError>),
+    /// A channel, thread or message that does not exist (anymore).
+    NotFound(Box<SerenityError>),
+    /// Any other error reported by Discord or serenity.
+    Discord(Box<SerenityError>),
+    /// Failure to read or write local files.
+    Io(std::io::Error),
+}
+
+impl BotError {
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            BotError::Discord(_) => 1,
+            BotError::Config(_) => 2,
+            BotError::Permission(_) => 3,
+            BotError::NotFound(_) => 4,
+            BotError::RateLimit(_) => 5,
+            BotError::Network(_) => 6,
+            BotError::Io(_) => 7,
+        })
+    }
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::Config(msg) => write!(f, "configuration error: {msg}"),
+            BotError::Permission(msg) => write!(f, "permission error: {msg}"),
+            BotError::RateLimit(err) => write!(f, "rate limited: {err}"),
+            BotError::Network(err) => write!(f, "network error: {err}"),
+            BotError::NotFound(err) => write!(f, "not found: {err}"),
+            BotError::Discord(err) => write!(f, "Discord error: {err}"),
+            BotError::Io(err) => write!(f, "I/O error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BotError {}
+
+impl From<SerenityError> for BotError {
+    fn from(err: SerenityError) -> Self {
+        match &err {
+            SerenityError::Http(HttpError::UnsuccessfulRequest(response)) => {
+                let code = response.error.code;
+                match response.status_code {
+                    StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                        BotError::Permission(err.to_string())
+                    }
+                    _ if code == MISSING_ACCESS || code == MISSING_PERMISSIONS => {
+                        BotError::Permission(err.to_string())
+                    }
+                    StatusCode::NOT_FOUND => BotError::NotFound(Box::new(err)),
+                    StatusCode::TOO_MANY_REQUESTS => BotError::RateLimit(Box::new(err)),
+                    status if status.is_server_error() => BotError::Network(Box::new(err)),
+                    _ => BotError::Discord(Box::new(err)),
+                }
+            }
+            SerenityError::Http(HttpError::Request(_)) | SerenityError::Io(_) => {
+                BotError::Network(Box::new(err))
+            }
+            SerenityError::Gateway(serenity::gateway::GatewayError::InvalidAuthentication) => {
+                BotError::Permission(format!("invalid token ({err})"))
+            }
+            _ => BotError::Discord(Box::new(err)),
+        }
+    }
+}
+
+impl From<std::io::Error> for BotError {
+    fn from(err: std::io::Error) -> Self {
+        BotError::Io(err)
+    }
+}
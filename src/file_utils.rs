@@ -0,0 +1,12 @@
+use std::path::Path;
+
+use crate::error::BotError;
+
+/// Write `contents` to `path` through a temporary file,
+/// so that an interrupted write never leaves a truncated file behind.
+pub fn write_atomically(path: &Path, contents: &str) -> Result<(), BotError> {
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
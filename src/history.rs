@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, MessageId};
+
+use crate::error::BotError;
+use crate::file_utils;
+use crate::state::RunState;
+use crate::toplist::{Entry, Stats};
+
+/// The posted weekly toplists, to build best-of toplists from,
+/// and the summary messages posted so far.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct History {
+    /// The ranked entries (highest first) per ISO week and toplist label.
+    #[serde(default)]
+    pub weeks: BTreeMap<String, BTreeMap<String, Vec<Entry>>>,
+    /// The activity per ISO week and toplist label.
+    #[serde(default)]
+    pub stats: BTreeMap<String, BTreeMap<String, Stats>>,
+    /// The summary message per period title, to not post it twice.
+    #[serde(default)]
+    pub summaries: BTreeMap<String, PostedSummary>,
+}
+
+/// A summary message posted before.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct PostedSummary {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+}
+
+impl History {
+    /// Read the history, which is empty if the file does not exist yet.
+    pub fn load(path: &Path) -> Result<History, BotError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).map_err(|err| {
+                BotError::Config(format!("invalid history {}: {err}", path.display()))
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(History::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Write the history to `path`, replacing it atomically.
+    pub fn save(&self, path: &Path) -> Result<(), BotError> {
+        let contents =
+            serde_json::to_string_pretty(self).map_err(|err| BotError::Io(err.into()))?;
+        file_utils::write_atomically(path, &contents)
+    }
+
+    /// Record the summary and, for a single week, the toplists of a posted run,
+    /// replacing those previously recorded for the same week and label.
+    pub fn record(&mut self, state: &RunState, summary_channel: ChannelId) {
+        if let Some(message_id) = state.summary_id {
+            self.summaries.insert(
+                state.title.clone(),
+                PostedSummary {
+                    channel_id: summary_channel,
+                    message_id,
+                },
+            );
+        }
+        let Some(week) = &state.week else {
+            return;
+        };
+        let lists = self.weeks.entry(week.clone()).or_default();
+        let stats = self.stats.entry(week.clone()).or_default();
+        for thread in &state.threads {
+            lists.insert(thread.label.clone(), thread.entries.clone());
+            if let Some(thread_stats) = thread.stats {
+                stats.insert(thread.label.clone(), thread_stats);
+            }
+        }
+        stats.extend(state.stats.clone());
+    }
+}
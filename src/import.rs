@@ -0,0 +1,317 @@
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use serenity::model::channel::Message;
+use serenity::model::id::UserId;
+
+use crate::error::BotError;
+use crate::source::MemorySource;
+
+/// A channel exported with DiscordChatExporter in JSON format.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChatExport {
+    guild: Id,
+    channel: Id,
+    messages: Vec<ExportedMessage>,
+}
+
+#[derive(Deserialize)]
+struct Id {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedMessage {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    timestamp: String,
+    timestamp_edited: Option<String>,
+    #[serde(default)]
+    is_pinned: bool,
+    #[serde(default)]
+    content: String,
+    author: Author,
+    #[serde(default)]
+    attachments: Vec<ExportedAttachment>,
+    #[serde(default)]
+    reactions: Vec<ExportedReaction>,
+    reference: Option<Reference>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Author {
+    id: String,
+    name: String,
+    #[serde(default)]
+    discriminator: Option<String>,
+    nickname: Option<String>,
+    #[serde(default)]
+    is_bot: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedAttachment {
+    id: String,
+    url: String,
+    file_name: String,
+    #[serde(default)]
+    file_size_bytes: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedReaction {
+    emoji: ExportedEmoji,
+    count: u64,
+    /// Only included by newer versions of DiscordChatExporter.
+    users: Option<Vec<Author>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedEmoji {
+    /// Empty for unicode emoji.
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    is_animated: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Reference {
+    message_id: Option<String>,
+    channel_id: Option<String>,
+    guild_id: Option<String>,
+}
+
+/// Add the messages of a DiscordChatExporter JSON export to `source`.
+///
+/// Exports without the users of each reaction get placeholder users instead,
+/// so that the 'Other' toplist counts the sum of reactions like before finalizing.
+///
+/// Returns the number of imported messages.
+pub fn import_chat_export(source: &mut MemorySource, path: &Path) -> Result<usize, BotError> {
+    let contents = std::fs::read_to_string(path)?;
+    let export: ChatExport = serde_json::from_str(&contents)
+        .map_err(|err| BotError::Config(format!("invalid export {}: {err}", path.display())))?;
+
+    let mut placeholder_user = u64::MAX;
+    let count = export.messages.len();
+    for exported in &export.messages {
+        let message: Message =
+            serde_json::from_value(to_api_message(&export, exported)).map_err(|err| {
+                BotError::Config(format!(
+                    "unsupported message {} in {}: {err}",
+                    exported.id,
+                    path.display()
+                ))
+            })?;
+
+        for reaction in &exported.reactions {
+            let users: Vec<UserId> = match &reaction.users {
+                Some(users) => users.iter().filter_map(|u| u.id.parse().ok()).collect(),
+                None => (0..reaction.count)
+                    .map(|_| {
+                        placeholder_user -= 1;
+                        UserId::new(placeholder_user)
+                    })
+                    .collect(),
+            };
+            source.set_reaction_users(message.id, reaction_key(&reaction.emoji), users);
+        }
+        source.add_message(message);
+    }
+    Ok(count)
+}
+
+/// The message as the Discord API would have returned it.
+fn to_api_message(export: &ChatExport, message: &ExportedMessage) -> Value {
+    // Forwards are messages of the default type with a reference,
+    // whereas replies and other references keep their own content.
+    let forward = message.kind == "Default";
+    let reference = message.reference.as_ref().map(|reference| {
+        json!({
+            "type": if forward { 1 } else { 0 },
+            "message_id": reference.message_id,
+            "channel_id": reference.channel_id.as_ref().unwrap_or(&export.channel.id),
+            "guild_id": reference.guild_id,
+        })
+    });
+    json!({
+        "id": message.id,
+        "channel_id": export.channel.id,
+        "guild_id": export.guild.id,
+        "author": {
+            "id": message.author.id,
+            "username": message.author.name,
+            "discriminator": message.author.discriminator.as_deref().unwrap_or("0000"),
+            "global_name": message.author.nickname,
+            "avatar": null,
+            "bot": message.author.is_bot,
+        },
+        "content": message.content,
+        "timestamp": message.timestamp,
+        "edited_timestamp": message.timestamp_edited,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": message.attachments.iter().map(|attachment| json!({
+            "id": attachment.id,
+            "filename": attachment.file_name,
+            "size": attachment.file_size_bytes,
+            "url": attachment.url,
+            "proxy_url": attachment.url,
+            "content_type": content_type(&attachment.file_name),
+        })).collect::<Vec<_>>(),
+        "embeds": [],
+        "pinned": message.is_pinned,
+        "type": if message.kind == "Reply" { 19 } else { 0 },
+        "reactions": message.reactions.iter().map(|reaction| json!({
+            "count": reaction.count,
+            "count_details": { "burst": 0, "normal": reaction.count },
+            "me": false,
+            "me_burst": false,
+            "burst_colors": [],
+            "emoji": match custom_emoji_id(&reaction.emoji) {
+                Some(id) => json!({
+                    "id": id,
+                    "name": reaction.emoji.name,
+                    "animated": reaction.emoji.is_animated,
+                }),
+                None => json!({ "id": null, "name": reaction.emoji.name }),
+            },
+        })).collect::<Vec<_>>(),
+        "message_reference": reference,
+    })
+}
+
+fn custom_emoji_id(emoji: &ExportedEmoji) -> Option<&str> {
+    emoji.id.as_deref().filter(|id| !id.is_empty())
+}
+
+/// The reaction as `ReactionType` displays it.
+fn reaction_key(emoji: &ExportedEmoji) -> String {
+    match custom_emoji_id(emoji) {
+        Some(id) if emoji.is_animated => format!("<a:{}:{id}>", emoji.name),
+        Some(id) => format!("<:{}:{id}>", emoji.name),
+        None => emoji.name.clone(),
+    }
+}
+
+/// Exports only include the file name, but images need a content type to be embedded.
+fn content_type(file_name: &str) -> Option<&'static str> {
+    let extension = file_name.rsplit_once('.')?.1.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use serenity::model::channel::MessageReferenceKind;
+    use serenity::model::id::MessageId;
+
+    use super::*;
+    use crate::config::Config;
+    use crate::retry::Retrier;
+    use crate::source::testing::CHANNEL_ID;
+    use crate::source::DiscordSource;
+    use crate::toplist::{Entry, Toplist};
+
+    fn import() -> MemorySource {
+        let mut source = MemorySource::default();
+        let path = Path::new("fixtures/chat-export.json");
+        assert_eq!(import_chat_export(&mut source, path).unwrap(), 3);
+        source
+    }
+
+    /// The toplist and 'Other' of the export, as contents and counts, highest first.
+    async fn toplists(source: &MemorySource, emoji: &str) -> [Vec<(String, u64)>; 2] {
+        let config: Config = toml::from_str(&format!(
+            r#"
+            channel_id = {CHANNEL_ID}
+            toplist = [{{ emoji = {emoji} }}]
+            other = {{ enabled = true }}
+            "#
+        ))
+        .unwrap();
+        let retrier = Retrier::new(&config.retry);
+        let mut toplist = Toplist::new(&config, &retrier, source, None);
+        let mut messages = source
+            .messages_after(CHANNEL_ID, MessageId::new(1), 100)
+            .await
+            .unwrap();
+        messages.reverse();
+        for message in &messages {
+            toplist.append(message).await;
+        }
+        toplist.finalize().await.unwrap();
+
+        let texts = |list: &BTreeSet<Entry>| {
+            list.iter()
+                .rev()
+                .map(|entry| (entry.content.joined(), entry.count))
+                .collect()
+        };
+        [
+            texts(&toplist.top[&config.toplist[0].emoji]),
+            texts(&toplist.other),
+        ]
+    }
+
+    #[tokio::test]
+    async fn toplists_from_export() {
+        let source = import();
+        let image = "https://cdn.discordapp.com/attachments/1/2/cat.png";
+
+        let [yes, other] = toplists(&source, r#"{ name = "yes", id = 1000377617683333210 }"#).await;
+        assert_eq!(
+            yes,
+            [
+                ("Replying to that".to_owned(), 2),
+                ("An old favourite".to_owned(), 1)
+            ]
+        );
+        // Counted by the placeholders of the users missing from the export.
+        assert_eq!(other, [(image.to_owned(), 2)]);
+
+        let [star, other] = toplists(&source, r#"{ string = "⭐" }"#).await;
+        assert_eq!(
+            star,
+            [("An old favourite".to_owned(), 3), (image.to_owned(), 2)]
+        );
+        // Counted by the users of the custom emoji, found by its `<:name:id>` key.
+        assert_eq!(other, [("Replying to that".to_owned(), 2)]);
+    }
+
+    #[tokio::test]
+    async fn replies_are_not_forwards() {
+        let reply = import()
+            .message(CHANNEL_ID, MessageId::new(1346912649216000000))
+            .await
+            .unwrap();
+        let reference = reply.message_reference.unwrap();
+        assert_eq!(reference.kind, MessageReferenceKind::Default);
+        assert_eq!(
+            reference.message_id,
+            Some(MessageId::new(1346029328793600000))
+        );
+        assert_eq!(reply.content, "Replying to that");
+    }
+}
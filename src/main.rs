@@ -1,34 +1,54 @@
 #![feature(slice_concat_trait)]
 
-use chrono::{DateTime, Utc};
-use serenity::all::{
-    AutoArchiveDuration, CreateAllowedMentions, CreateEmbed, CreateMessage, CreateThread,
-    GetMessages,
-};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use clap::Parser;
+use serenity::all::{AutoArchiveDuration, CreateThread, EditThread, GetMessages};
+use serenity::futures::{stream, StreamExt};
+use serenity::http::{Http, HttpBuilder};
 use serenity::model::gateway::GatewayIntents;
-use serenity::model::id::GuildId;
+use serenity::model::id::{ChannelId, GuildId, UserId};
 use serenity::model::prelude::CurrentUser;
 use serenity::{
     async_trait,
     gateway::ShardManager,
     model::{
-        channel::{ChannelType, GuildChannel},
+        channel::{ChannelType, GuildChannel, Message, MessageType},
         gateway::Ready,
         id::MessageId,
     },
     prelude::{Client, Context, EventHandler, SerenityError, TypeMapKey},
 };
-use std::env;
-use std::error::Error;
-use std::path::Path;
-use std::{collections::BTreeSet, sync::Arc};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::process::ExitCode;
+use std::sync::Arc;
 
+mod cache;
+mod cli;
 mod config;
+mod error;
+mod file_utils;
+mod history;
+mod import;
+mod preflight;
+mod render;
+mod retry;
+mod source;
+mod state;
 mod time_utils;
 mod toplist;
+mod validate;
 
-use config::{Config, Emoji};
-use toplist::{MsgWrap, Toplist};
+use cache::CachedSource;
+use cli::{Cli, Command, OnExisting, Options};
+use config::{Channels, Config, Emoji, Layout};
+use error::BotError;
+use history::History;
+use render::Winner;
+use retry::Retrier;
+use source::{DiscordSource, HttpSource, MemorySource};
+use state::{RunState, ThreadState};
+use time_utils::Period;
+use toplist::{Entry, Stats, Toplist};
 
 // https://discord.com/developers/docs/events/gateway#gateway-intents
 const GATEWAY_INTENTS: GatewayIntents = GatewayIntents::GUILDS
@@ -36,25 +56,119 @@ const GATEWAY_INTENTS: GatewayIntents = GatewayIntents::GUILDS
     .union(GatewayIntents::GUILD_MESSAGE_TYPING);
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let config = Config::from_path(Path::new("./config.toml"))?;
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            err.exit_code()
+        }
+    }
+}
 
-    // Parse week from command line argument
-    let week_param = std::env::args().nth(1);
-    let week_param_str = week_param.as_ref().map(|s| s.as_str());
-    let options = Options {
-        calendar_week: time_utils::parse_iso_week(week_param_str)?,
-    };
+async fn run() -> Result<(), BotError> {
+    let cli = Cli::parse();
+    let mut configs = Config::from_path(&cli.config)?;
+    for name in &cli.profile {
+        if !configs.iter().any(|config| config.name == *name) {
+            return Err(BotError::Config(format!("unknown profile `{name}`")));
+        }
+    }
+    if !cli.profile.is_empty() {
+        configs.retain(|config| cli.profile.contains(&config.name));
+    }
+    if let Some(channel_id) = cli.target_channel {
+        for config in &mut configs {
+            config.override_target_channel(channel_id);
+        }
+    }
 
-    eprintln!("Config: {:?}", config);
+    let options = cli.options()?;
+
+    for config in &configs {
+        eprintln!("Config: {:?}", config);
+    }
     eprintln!("Options: {:?}", options);
 
+    if options.command == Command::Validate {
+        return validate_config(&mut configs).await;
+    }
+    check_only(&configs, &options.only)?;
+    if matches!(options.command, Command::Post { .. }) && configs.len() > 1 {
+        return Err(BotError::Config(
+            "select the profile to post for with `--profile`".to_owned(),
+        ));
+    }
+
+    // A best-of from the history only needs Discord for posting.
+    let from_history = options.command == Command::BestOf { rescan: false } && options.dry_run;
+    if options.fixture.is_some() || !options.import.is_empty() || from_history {
+        let mut source = match &options.fixture {
+            Some(fixture) => MemorySource::from_path(fixture)?,
+            None => MemorySource::default(),
+        };
+        for path in &options.import {
+            let count = import::import_chat_export(&mut source, path)?;
+            eprintln!("Imported {count} messages from {}", path.display());
+        }
+        // Without Discord, only the custom emoji used in the reactions are known.
+        if options.fixture.is_some() || !options.import.is_empty() {
+            let available = source.custom_emojis();
+            for config in &mut configs {
+                let unresolved = config.resolve_named_emojis(&available);
+                if !unresolved.is_empty() {
+                    return Err(BotError::Config(format!(
+                        "custom emoji of profile `{}` not found in any reaction: {}",
+                        config.name,
+                        unresolved.join(", ")
+                    )));
+                }
+            }
+        }
+        for counter in ReactionCounter::for_profiles(configs, &options) {
+            for state in counter.collect(&source, None).await? {
+                counter.output(&state)?;
+            }
+        }
+        return Ok(());
+    }
+
     // Login with a bot token from the environment
-    let token = env::var("DISCORD_TOKEN").expect("token missing");
+    let token = configs[0].token.clone().ok_or_else(|| {
+        BotError::Config("neither `DISCORD_TOKEN` nor `DISCORD_TOKEN_FILE` is set".to_owned())
+    })?;
+    let token = token.as_str();
+
+    for config in &mut configs {
+        if config.has_named_emojis() {
+            let unresolved = resolve_named_emojis(config, &http_client(config, token)).await?;
+            if !unresolved.is_empty() {
+                return Err(BotError::Config(format!(
+                    "custom emoji not found in the server of profile `{}`: {}",
+                    config.name,
+                    unresolved.join(", ")
+                )));
+            }
+        }
+    }
+
+    let api_url = configs[0].api_url.clone();
+    let counters = ReactionCounter::for_profiles(configs, &options);
+    if let Some(api_url) = api_url {
+        // Without the gateway, e.g. against a mock server.
+        eprintln!("Using the Discord API at {api_url}");
+        let http = Arc::new(http_client(&counters[0].config, token));
+        let user = http.get_current_user().await?;
+        let session = Session {
+            http,
+            user_id: user.id,
+        };
+        return run_profiles(&counters, &session).await;
+    }
+
     let mut client = Client::builder(token, GATEWAY_INTENTS)
-        .event_handler(ReactionCounter { config, options })
-        .await
-        .expect("Error creating client");
+        .event_handler(Bot { counters })
+        .await?;
 
     {
         // Insert shard manager so we can shut it down from within an event handler
@@ -62,15 +176,91 @@ async fn main() -> Result<(), Box<dyn Error>> {
         data.insert::<ShardManagerContainer>(client.shard_manager.clone());
     }
 
-    if let Err(why) = client.start().await {
-        eprintln!("An error occurred while running the client: {:?}", why);
+    client.start().await?;
+
+    let mut data = client.data.write().await;
+    data.remove::<RunResultContainer>()
+        .unwrap_or_else(|| Err(SerenityError::Other("client stopped before finishing").into()))
+}
+
+/// Check the configuration and print all problems found,
+/// also checking against Discord if a token is available.
+async fn validate_config(configs: &mut [Config]) -> Result<(), BotError> {
+    if configs.iter().any(|config| config.token.is_none()) {
+        eprintln!("No token set, skipping online checks");
+    }
+    let multiple = configs.len() > 1;
+    let mut errors = 0;
+    let mut warnings = 0;
+    for config in configs {
+        let mut diagnostics = validate::check_offline(config);
+        if let Some(token) = config.token.clone() {
+            let http = http_client(config, token.as_str());
+            let retrier = Retrier::new(&config.retry);
+            if config.has_named_emojis() {
+                match resolve_named_emojis(config, &http).await {
+                    Ok(unresolved) => diagnostics.extend(
+                        unresolved
+                            .iter()
+                            .map(|name| validate::unresolved_emoji(name)),
+                    ),
+                    Err(err) => diagnostics.push(validate::unresolvable_emojis(&err)),
+                }
+            }
+            diagnostics.extend(validate::check_online(config, &retrier, &http).await);
+        }
+
+        if multiple {
+            println!("profile `{}`:", config.name);
+        }
+        for diagnostic in &diagnostics {
+            println!("{diagnostic}");
+        }
+        let profile_errors = diagnostics
+            .iter()
+            .filter(|d| d.level == validate::Level::Error)
+            .count();
+        errors += profile_errors;
+        warnings += diagnostics.len() - profile_errors;
+    }
+    println!("{} error(s), {} warning(s)", errors, warnings);
+
+    if errors > 0 {
+        Err(BotError::Config(format!("found {errors} error(s)")))
+    } else {
+        Ok(())
+    }
+}
+
+fn http_client(config: &Config, token: &str) -> Http {
+    let builder = HttpBuilder::new(token);
+    match &config.api_url {
+        // Serenity's ratelimiter ignores the proxy setting.
+        Some(api_url) => builder.proxy(api_url).ratelimiter_disabled(true).build(),
+        None => builder.build(),
     }
-    Ok(())
 }
 
-#[derive(Debug)]
-struct Options {
-    calendar_week: chrono::IsoWeek,
+/// Look up custom emoji configured by name in the guild of `channel_id`.
+///
+/// Returns the names that could not be found.
+async fn resolve_named_emojis(config: &mut Config, http: &Http) -> Result<Vec<String>, BotError> {
+    let retrier = Retrier::new(&config.retry);
+    let channel_id = config.channel_id;
+    let guild_id = retrier
+        .run("fetching channel", || channel_id.to_channel(http))
+        .await?
+        .guild()
+        .ok_or_else(|| BotError::Config(format!("{channel_id} is not a guild channel")))?
+        .guild_id;
+    let guild_emojis = retrier
+        .run("fetching guild emojis", || guild_id.emojis(http))
+        .await?;
+    let available = guild_emojis
+        .into_iter()
+        .map(|emoji| (emoji.name, emoji.id))
+        .collect();
+    Ok(config.resolve_named_emojis(&available))
 }
 
 /// Wrapping to be able to shutdown the client from within an event handler.
@@ -88,15 +278,35 @@ impl TypeMapKey for CurrentUserContainer {
     type Value = CurrentUser;
 }
 
+/// The outcome of the run inside the event handler, to be reported by `main`.
+struct RunResultContainer;
+
+impl TypeMapKey for RunResultContainer {
+    type Value = Result<(), BotError>;
+}
+
+/// Processes all profiles one after another on the same connection.
+struct Bot {
+    counters: Vec<ReactionCounter>,
+}
+
+/// The connection to Discord a run works with.
+struct Session {
+    http: Arc<Http>,
+    /// The bot's own user.
+    user_id: UserId,
+}
+
 struct ReactionCounter {
-    /// file-based configuration
+    /// file-based configuration of one profile
     config: Config,
+    retrier: Retrier,
     /// command-line arguments
     options: Options,
 }
 
 #[async_trait]
-impl EventHandler for ReactionCounter {
+impl EventHandler for Bot {
     async fn ready(&self, ctx: Context, ready: Ready) {
         eprintln!("Connected as {}! Waiting for cache...", ready.user.name);
         {
@@ -110,199 +320,1164 @@ impl EventHandler for ReactionCounter {
 
         let user = {
             let data = ctx.data.read().await;
-            data.get::<CurrentUserContainer>().unwrap().clone()
+            data.get::<CurrentUserContainer>().map(|user| user.id)
+        };
+        let result = match user {
+            Some(user_id) => {
+                let session = Session {
+                    http: ctx.http.clone(),
+                    user_id,
+                };
+                run_profiles(&self.counters, &session).await
+            }
+            None => Err(BotError::Config("current user unknown".to_owned())),
         };
+        {
+            let mut data = ctx.data.write().await;
+            data.insert::<RunResultContainer>(result);
+        }
 
-        let toplist = self.scan_channel(&ctx, &user).await;
+        self.shutdown(&ctx).await;
+    }
+}
 
-        let typing = self.config.target_channel_id().start_typing(&ctx.http);
+/// Run every profile, report the outcome of each and fail with the first error.
+async fn run_profiles(counters: &[ReactionCounter], session: &Session) -> Result<(), BotError> {
+    let mut outcomes = Vec::new();
+    for counter in counters {
+        if counters.len() > 1 {
+            eprintln!("Processing profile {:?}", counter.config.name);
+        }
+        outcomes.push(counter.run(session).await);
+    }
 
-        let emoji_to_post: Vec<_> = self.config.toplist.iter().map(|item| &item.emoji).collect();
+    let mut result = Ok(());
+    for (counter, outcome) in counters.iter().zip(outcomes) {
+        match outcome {
+            Ok(()) => eprintln!("Profile {:?}: done", counter.config.name),
+            Err(err) => {
+                eprintln!("Profile {:?}: failed: {err}", counter.config.name);
+                if result.is_ok() {
+                    result = Err(err);
+                }
+            }
+        }
+    }
+    result
+}
 
-        for key in emoji_to_post {
-            if let Some(list) = toplist.top.get(&key) {
-                self.post_toplist_thread(&ctx, &Some(key.clone()), list)
-                    .await
-                    .expect("unable to create message");
+impl Bot {
+    async fn shutdown(&self, ctx: &Context) {
+        let data = ctx.data.read().await;
+        if let Some(manager) = data.get::<ShardManagerContainer>() {
+            eprintln!("Shutting down...");
+            manager.shutdown_all().await;
+        } else {
+            eprintln!("There was a problem getting the shard manager");
+        }
+    }
+}
+
+impl ReactionCounter {
+    fn for_profiles(configs: Vec<Config>, options: &Options) -> Vec<ReactionCounter> {
+        let multiple = configs.len() > 1;
+        configs
+            .into_iter()
+            .map(|config| {
+                let mut options = options.clone();
+                if let Command::Export { output }
+                | Command::Backfill {
+                    export: Some(output),
+                } = &mut options.command
+                {
+                    if multiple {
+                        *output = config::profile_path(output, &config.name);
+                    }
+                }
+                ReactionCounter {
+                    retrier: Retrier::new(&config.retry),
+                    config,
+                    options,
+                }
+            })
+            .collect()
+    }
+
+    async fn run(&self, ctx: &Session) -> Result<(), BotError> {
+        let command = &self.options.command;
+        let state_file = &self.config.state_file;
+        if *command == Command::Resume && !state_file.exists() {
+            // With several profiles, usually only one was interrupted.
+            eprintln!("Nothing to resume, {:?} does not exist", state_file);
+            return Ok(());
+        }
+        let posting = !self.options.dry_run && *command != Command::Resume;
+        if posting && !self.options.force && state_file.exists() {
+            return Err(BotError::Config(format!(
+                "{} is left from an interrupted run, \
+                 continue it with `resume` or start over with `--force`",
+                state_file.display()
+            )));
+        }
+        let scanning = matches!(
+            command,
+            Command::Run
+                | Command::Scan
+                | Command::Export { .. }
+                | Command::Backfill { .. }
+                | Command::BestOf { rescan: true }
+        );
+        preflight::check_permissions(
+            &self.config,
+            &self.retrier,
+            &ctx.http,
+            ctx.user_id,
+            preflight::Needs {
+                source: scanning,
+                target: !self.options.dry_run,
+                reuse_threads: self.options.on_existing != OnExisting::Duplicate,
+            },
+        )
+        .await?;
+
+        let states = match command {
+            Command::Run
+            | Command::Scan
+            | Command::Export { .. }
+            | Command::Backfill { .. }
+            | Command::BestOf { .. } => {
+                let source = HttpSource::new(ctx.http.clone());
+                if self.config.cache.enabled {
+                    let source = CachedSource::load(source, &self.config.cache);
+                    let result = self.collect(&source, Some(ctx.user_id)).await;
+                    // Keep what was fetched even if the scan failed.
+                    source.save()?;
+                    result?
+                } else {
+                    self.collect(&source, Some(ctx.user_id)).await?
+                }
+            }
+            Command::Post { input } => {
+                eprintln!("Posting from {:?}", input);
+                vec![RunState::load(input)?]
+            }
+            Command::Resume => {
+                eprintln!("Resuming from {:?}", self.config.state_file);
+                vec![RunState::load(&self.config.state_file)?]
+            }
+            Command::Validate => unreachable!("handled before connecting"),
+        };
+
+        let exporting = matches!(command, Command::Backfill { export: Some(_) });
+        if self.options.dry_run || exporting {
+            for state in &states {
+                self.output(state)?;
             }
         }
-        if self.config.other.enabled {
-            self.post_toplist_thread(&ctx, &None, &toplist.other)
-                .await
-                .expect("unable to create message");
+        if self.options.dry_run {
+            return Ok(());
         }
 
+        let typing = self.config.target_channel_id().start_typing(&ctx.http);
+        let mut result = Ok(());
+        for state in states {
+            eprintln!("Posting toplists for {}", state.title);
+            result = self.post(ctx, state).await;
+            if result.is_err() {
+                break;
+            }
+        }
         typing.stop();
+        result
+    }
 
-        self.shutdown(&ctx).await;
+    /// The periods to build separate toplists for.
+    fn periods(&self) -> Vec<Period> {
+        match self.options.command {
+            Command::Backfill { .. } | Command::BestOf { .. } => self.options.period.split_weeks(),
+            _ => vec![self.options.period.clone()],
+        }
     }
-}
 
-impl ReactionCounter {
-    async fn scan_channel<'c>(&'c self, ctx: &Context, user: &CurrentUser) -> Toplist<'c> {
-        let channel_id = self.config.channel_id;
+    /// Scan the channels and render the toplists of every period,
+    /// or of the whole period for a best-of.
+    async fn collect(
+        &self,
+        source: &dyn DiscordSource,
+        user_id: Option<UserId>,
+    ) -> Result<Vec<RunState>, BotError> {
+        let periods = self.periods();
+        let mut stats = Vec::new();
+        let weekly: Vec<_> = match self.options.command {
+            Command::BestOf { rescan: false } => {
+                let mut history = History::load(&self.config.history_file)?;
+                let missing: Vec<_> = periods
+                    .iter()
+                    .filter(|week| !history.weeks.contains_key(&week.label))
+                    .map(|week| week.label.as_str())
+                    .collect();
+                if !missing.is_empty() {
+                    eprintln!(
+                        "No toplists recorded for {} of {} weeks: {}",
+                        missing.len(),
+                        periods.len(),
+                        missing.join(", ")
+                    );
+                }
+                periods
+                    .iter()
+                    .map(|week| {
+                        let lists = history.weeks.remove(&week.label).unwrap_or_default();
+                        lists
+                            .into_iter()
+                            .map(|(label, entries)| (label, entries.into_iter().collect()))
+                            .collect()
+                    })
+                    .collect()
+            }
+            _ => {
+                let toplists = self.scan(source, user_id, &periods).await?;
+                stats = toplists
+                    .iter()
+                    .map(|toplist| self.stats_by_label(toplist))
+                    .collect();
+                toplists.into_iter().map(lists_by_label).collect()
+            }
+        };
+
+        if let Command::BestOf { .. } = self.options.command {
+            let best = self.best_of(weekly);
+            return Ok(vec![self.plan(&self.options.period, &best)]);
+        }
+
+        // Each week is compared to the one before,
+        // which is either recorded in the history or also part of this run.
+        let mut previous = self.previous_stats(&periods[0])?;
+        let mut states = Vec::new();
+        for ((period, lists), stats) in periods.iter().zip(&weekly).zip(stats) {
+            let mut state = self.plan(period, lists);
+            // Toplists without a thread still had (no) activity to compare with.
+            state.stats = stats.into_iter().collect();
+            for thread in &mut state.threads {
+                let thread_stats = state.stats.entry(thread.label.clone()).or_default();
+                thread_stats.winner = thread.winner.as_ref().map_or(0, |winner| winner.count);
+                thread.stats = Some(*thread_stats);
+                thread.previous = previous.get(&thread.label).copied();
+            }
+            previous = state.stats.clone();
+            states.push(state);
+        }
+        Ok(states)
+    }
+
+    /// The activity on every selected toplist, by their label.
+    fn stats_by_label(&self, toplist: &Toplist) -> HashMap<String, Stats> {
+        self.config
+            .toplist
+            .iter()
+            .map(|entry| Some(entry.emoji.clone()))
+            .chain(self.config.other.enabled.then_some(None))
+            .filter(|emoji| self.is_selected(emoji))
+            .map(|emoji| (emoji_label(&emoji).to_owned(), toplist.stats(&emoji)))
+            .collect()
+    }
+
+    /// The recorded activity of the week before `period`, if it is a single week.
+    fn previous_stats(&self, period: &Period) -> Result<BTreeMap<String, Stats>, BotError> {
+        if period.as_week().is_none() {
+            return Ok(BTreeMap::new());
+        }
+        let previous = Period::week((period.start - Duration::weeks(1)).iso_week());
+        let mut history = History::load(&self.config.history_file)?;
+        Ok(history.stats.remove(&previous.label).unwrap_or_default())
+    }
+
+    /// Combine the weekly toplists into the best of all weeks.
+    ///
+    /// Only entries ranked `best_of.eligible_rank` or better in their week are eligible,
+    /// equal counts sharing a rank.
+    /// They are then ranked by the number of users that reacted during their week.
+    fn best_of(
+        &self,
+        weekly: Vec<HashMap<String, BTreeSet<Entry>>>,
+    ) -> HashMap<String, BTreeSet<Entry>> {
+        let eligible_rank = self.config.best_of.eligible_rank;
+        let mut eligible: HashMap<String, HashMap<MessageId, Entry>> = HashMap::new();
+        for lists in weekly {
+            for (label, list) in lists {
+                let best = eligible.entry(label).or_default();
+                for item in list.iter().rev() {
+                    let rank = 1 + list.iter().filter(|other| other.count > item.count).count();
+                    if rank > eligible_rank {
+                        break;
+                    }
+                    // With overlapping history, a message may have been ranked twice.
+                    if best
+                        .get(&item.id)
+                        .is_none_or(|known| known.count < item.count)
+                    {
+                        best.insert(item.id, item.clone());
+                    }
+                }
+            }
+        }
+
+        eligible
+            .into_iter()
+            .map(|(label, entries)| {
+                let mut list: BTreeSet<_> = entries.into_values().collect();
+                while list.len() > self.max_entries(&label) {
+                    list.pop_first();
+                }
+                (label, list)
+            })
+            .collect()
+    }
+
+    /// The configured number of entries of the toplist with the given label.
+    fn max_entries(&self, label: &str) -> usize {
+        self.config
+            .toplist
+            .iter()
+            .find(|entry| emoji_as_string(&entry.emoji) == label)
+            .map_or(self.config.other.max, |entry| entry.max)
+    }
+
+    /// Write the toplists to the file given to `export` or print them.
+    fn output(&self, state: &RunState) -> Result<(), BotError> {
+        let output = match &self.options.command {
+            Command::Export { output } => output.clone(),
+            Command::Backfill {
+                export: Some(export),
+            } => config::profile_path(export, &state.title),
+            _ => {
+                print_plan(state);
+                return Ok(());
+            }
+        };
+        eprintln!("Writing toplists to {:?}", output);
+        state.save(&output)
+    }
+
+    /// Scan every source channel of the selected toplists once,
+    /// counting each message into the toplist of the period it was sent in.
+    async fn scan<'c>(
+        &'c self,
+        source: &'c dyn DiscordSource,
+        user_id: Option<UserId>,
+        periods: &[Period],
+    ) -> Result<Vec<Toplist<'c>>, BotError> {
+        let config = &self.config;
+        let selected = config
+            .toplist
+            .iter()
+            .filter(|entry| self.is_selected(&Some(entry.emoji.clone())))
+            .map(|entry| &entry.channels)
+            .chain(
+                (config.other.enabled && self.is_selected(&None)).then_some(&config.other.channels),
+            );
+        let channel_ids: BTreeSet<_> = selected
+            .map(|channels| config.source_channel(channels))
+            .collect();
+
+        let mut toplists: Vec<_> = periods
+            .iter()
+            .map(|_| {
+                Toplist::new(config, &self.retrier, source, user_id)
+                    .with_other(self.is_selected(&None))
+            })
+            .collect();
+        for channel_id in channel_ids {
+            self.scan_channel(source, channel_id, periods, &mut toplists)
+                .await?;
+        }
+        for (period, toplist) in periods.iter().zip(&mut toplists) {
+            if periods.len() > 1 {
+                eprintln!("Finalizing toplists for {}", period.label);
+            }
+            toplist.finalize().await?;
+        }
+
+        eprintln!("Finished collecting messages");
+        Ok(toplists)
+    }
+
+    async fn scan_channel(
+        &self,
+        source: &dyn DiscordSource,
+        channel_id: ChannelId,
+        periods: &[Period],
+        toplists: &mut [Toplist<'_>],
+    ) -> Result<(), BotError> {
+        let (Some(first), Some(last)) = (periods.first(), periods.last()) else {
+            return Ok(());
+        };
+        let (start, end) = (first.start, last.end);
         eprintln!(
-            "Scanning channel {:?} over {:?}",
-            channel_id, self.options.calendar_week
+            "Scanning channel {:?} over {}",
+            channel_id, self.options.period.label
         );
+        eprintln!("Time span: {:?} til {:?}", start, end);
 
-        let start_time = time_utils::iso_week_to_datetime(self.options.calendar_week);
-        let end_time = start_time + chrono::Duration::weeks(1);
-        eprintln!("Time span: {:?} til {:?}", start_time, end_time);
+        let scan = &self.config.scan;
+        let slice_length = Duration::hours(scan.slice_hours.max(1).into());
+        let mut slices = Vec::new();
+        let mut slice_start = start;
+        while slice_start < end {
+            let slice_end = (slice_start + slice_length).min(end);
+            slices.push((slice_start, slice_end));
+            slice_start = slice_end;
+        }
 
-        let mut toplist = Toplist::new(&self.config, user, ctx.http.clone());
-        let mut first_id: MessageId = (time_utils::time_snowflake(start_time, false) - 1).into();
+        // Slices are fetched concurrently, but merged in order,
+        // so that ties keep the earlier message like a sequential scan would.
+        let mut fetched = stream::iter(slices)
+            .map(|(start, end)| self.fetch_slice(source, channel_id, start, end))
+            .buffered(scan.concurrency.max(1));
+        while let Some(msgs) = fetched.next().await {
+            for msg in &msgs? {
+                let timestamp: DateTime<Utc> = *msg.timestamp;
+                let i = periods.partition_point(|period| period.end <= timestamp);
+                if periods
+                    .get(i)
+                    .is_some_and(|period| period.start <= timestamp)
+                {
+                    toplists[i].append(msg).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The messages with reactions sent from `start` (inclusive) to `end` (exclusive),
+    /// oldest first.
+    async fn fetch_slice(
+        &self,
+        source: &dyn DiscordSource,
+        channel_id: ChannelId,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Message>, BotError> {
+        let mut first_id: MessageId = (time_utils::time_snowflake(start, false) - 1).into();
+        let mut slice = Vec::new();
 
-        'outer: for page in 1.. {
+        for page in 1.. {
             eprintln!(
-                "Fetching page {} (after {})",
+                "Fetching page {} of {} (after {})",
                 page,
+                start,
                 time_utils::snowflake_time(first_id)
             );
-            let msgs = channel_id
-                .messages(&ctx.http, GetMessages::new().after(first_id).limit(100))
-                .await
-                .unwrap();
+            let msgs = self
+                .retrier
+                .run("fetching messages", || {
+                    source.messages_after(channel_id, first_id, 100)
+                })
+                .await?;
             eprintln!("Retrieved {} messages", msgs.len());
 
-            // Messages are returned newest to oldest
-            first_id = match msgs.first() {
-                Some(first) => first.id.into(),
+            // Don't rely on the order of the page;
+            // it may straddle the end of the slice in any order.
+            first_id = match msgs.iter().map(|msg| msg.id).max() {
+                Some(last) => last,
                 None => break,
             };
 
-            for msg in &msgs {
-                if (&msg.timestamp as &DateTime<Utc>) > &end_time {
-                    break 'outer;
+            let mut reached_end = false;
+            for msg in msgs {
+                let timestamp: &DateTime<Utc> = &msg.timestamp;
+                if *timestamp < start {
+                    continue;
+                }
+                if *timestamp >= end {
+                    reached_end = true;
+                    continue;
                 }
                 if !msg.reactions.is_empty() {
-                    toplist.append(msg).await;
+                    slice.push(msg);
                 }
             }
+            if reached_end {
+                break;
+            }
         }
+        slice.sort_by_key(|msg| msg.id);
+        Ok(slice)
+    }
 
-        toplist
-            .finalize()
-            .await
-            .expect("unable to fetch reaction users");
+    /// Render all toplists into the posts to send.
+    fn plan(&self, period: &Period, lists: &HashMap<String, BTreeSet<Entry>>) -> RunState {
+        let mut threads = Vec::new();
+        for item in &self.config.toplist {
+            if !self.is_selected(&Some(item.emoji.clone())) {
+                continue;
+            }
+            if let Some(list) = lists.get(emoji_as_string(&item.emoji)) {
+                threads.push(self.plan_thread(
+                    period,
+                    &Some(item.emoji.clone()),
+                    list,
+                    item.layout,
+                    &item.channels,
+                ));
+            }
+        }
+        if self.config.other.enabled && self.is_selected(&None) {
+            let empty = BTreeSet::new();
+            threads.push(self.plan_thread(
+                period,
+                &None,
+                lists.get(emoji_label(&None)).unwrap_or(&empty),
+                self.config.other.layout,
+                &self.config.other.channels,
+            ));
+        }
 
-        eprintln!("Finished collecting messages");
-        toplist
+        RunState {
+            title: period.label.clone(),
+            week: period.as_week().map(|week| format!("{:?}", week)),
+            threads,
+            stats: BTreeMap::new(),
+            summary_pending: self.config.summary.enabled,
+            summary_id: None,
+        }
     }
 
-    async fn post_toplist_thread(
+    /// Whether the toplist for `emoji` was selected with `--only`.
+    fn is_selected(&self, emoji: &Option<Emoji>) -> bool {
+        let only = &self.options.only;
+        if only.is_empty() {
+            return true;
+        }
+        let label = emoji_label(emoji);
+        only.iter().any(|selected| selects(selected, label))
+    }
+
+    fn plan_thread(
         &self,
-        ctx: &Context,
+        period: &Period,
         emoji: &Option<Emoji>,
-        list: &BTreeSet<MsgWrap>,
-    ) -> Result<(), SerenityError> {
-        let thread = self.create_thread(ctx, emoji).await;
+        list: &BTreeSet<Entry>,
+        layout: Layout,
+        channels: &Channels,
+    ) -> ThreadState {
+        ThreadState {
+            channel_id: Some(self.config.target_channel(channels)),
+            label: emoji_label(emoji).to_owned(),
+            name: thread_name(period, emoji),
+            winner: list.last().map(Winner::from),
+            posts: render::render_toplist(list, layout),
+            entries: list.iter().rev().cloned().collect(),
+            stats: None,
+            previous: None,
+            thread_id: None,
+            sent: 0,
+        }
+    }
 
-        eprintln!("Starting to populate thread for {:?}", emoji);
+    /// Send everything that has not been sent yet,
+    /// checkpointing the progress to the state file.
+    async fn post(&self, ctx: &Session, mut state: RunState) -> Result<(), BotError> {
+        let state_file = &self.config.state_file;
+        state.save(state_file)?;
 
-        let items_with_rank: Vec<_> = list
-            .iter()
-            .rev()
-            .enumerate()
-            .scan((0, 0), |(rank, count), (i, item)| {
-                if *count != item.count {
-                    *rank = i + 1;
-                }
-                *count = item.count;
-                Some((item, *rank))
-            })
-            .collect();
+        for i in 0..state.threads.len() {
+            if state.threads[i].is_done() {
+                eprintln!("Thread {:?} already done", state.threads[i].name);
+                continue;
+            }
+            self.post_thread(ctx, &mut state, i).await?;
+        }
 
-        for (item, rank) in items_with_rank.into_iter().rev() {
-            thread
-                .send_message(
-                    &ctx.http,
-                    CreateMessage::new().content(format!(
-                        "```c\n{} // {} user{}\n```",
-                        rank,
-                        item.count,
-                        if item.count == 1 { "" } else { "s" },
-                    )),
-                )
-                .await?;
+        if state.summary_pending {
+            state.summary_id = Some(self.post_summary(ctx, &state).await?);
+            state.summary_pending = false;
+            state.save(state_file)?;
+        }
 
-            thread
-                .send_message(
-                    &ctx.http,
-                    CreateMessage::new()
-                        .content(format!("{}", &item.content))
-                        .allowed_mentions(CreateAllowedMentions::new()),
-                )
+        if state.week.is_some() || state.summary_id.is_some() {
+            let history_file = &self.config.history_file;
+            eprintln!("Recording {} in {:?}", state.title, history_file);
+            let mut history = History::load(history_file)?;
+            history.record(&state, self.config.target_channel_id());
+            history.save(history_file)?;
+        }
+
+        RunState::remove(state_file)?;
+        Ok(())
+    }
+
+    async fn post_thread(
+        &self,
+        ctx: &Session,
+        state: &mut RunState,
+        i: usize,
+    ) -> Result<(), BotError> {
+        let state_file = &self.config.state_file;
+        let thread_id = match state.threads[i].thread_id {
+            Some(thread_id) => {
+                eprintln!(
+                    "Continuing thread {:?} after {} messages",
+                    thread_id, state.threads[i].sent
+                );
+                thread_id
+            }
+            None => {
+                let (thread_id, sent) = self.open_thread(ctx, &state.threads[i]).await?;
+                state.threads[i].thread_id = Some(thread_id);
+                state.threads[i].sent = sent;
+                state.save(state_file)?;
+                thread_id
+            }
+        };
+
+        let name = state.threads[i].name.clone();
+        eprintln!("Starting to populate thread {:?}", name);
+        while let Some(post) = state.threads[i].posts.get(state.threads[i].sent) {
+            self.retrier
+                .run("sending message", || {
+                    thread_id.send_message(&ctx.http, post.to_create())
+                })
                 .await?;
+            state.threads[i].sent += 1;
+            state.save(state_file)?;
+        }
+        eprintln!("Done populating thread {:?}", name);
+        Ok(())
+    }
 
-            let reaction_strs: Vec<_> = item
-                .message
-                .reactions
-                .iter()
-                .map(|r| format!("{} {}", &r.reaction_type, r.count - r.me as u64))
-                .collect();
-            thread
-                .send_message(
-                    &ctx.http,
-                    CreateMessage::new()
-                        .content(format!(
-                            "by {} ({})",
-                            &item.message.author, &item.message.author.name
-                        ))
-                        .embed(CreateEmbed::new().title("  ").description(format!(
-                            "{} | [link]({})",
-                            reaction_strs.join(" | "),
-                            item.message.link()
-                        )))
-                        .allowed_mentions(CreateAllowedMentions::new()),
-                )
+    /// Create the thread or, depending on `--on-existing`, reuse an existing one.
+    ///
+    /// Returns the thread and the number of posts that are already present in it.
+    async fn open_thread(
+        &self,
+        ctx: &Session,
+        plan: &ThreadState,
+    ) -> Result<(ChannelId, usize), BotError> {
+        let channel_id = plan
+            .channel_id
+            .unwrap_or_else(|| self.config.target_channel_id());
+        let channel = self
+            .retrier
+            .run("fetching target channel", || {
+                channel_id.to_channel(&ctx.http)
+            })
+            .await?
+            .guild()
+            .ok_or_else(|| BotError::Config(format!("{channel_id} is not a guild channel")))?;
+
+        let existing = match self.options.on_existing {
+            OnExisting::Duplicate => None,
+            _ => self.find_existing_thread(ctx, &channel, &plan.name).await?,
+        };
+        let Some(mut thread) = existing else {
+            let thread = self.create_thread(ctx, &channel, plan.name.clone()).await?;
+            return Ok((thread.id, 0));
+        };
+
+        if self.options.on_existing == OnExisting::Skip {
+            eprintln!(
+                "Skipping existing thread {:?} for {:?}",
+                thread.id, plan.name
+            );
+            return Ok((thread.id, plan.posts.len()));
+        }
+
+        if thread.thread_metadata.is_some_and(|meta| meta.archived) {
+            eprintln!("Unarchiving thread {:?}", thread.id);
+            thread = self
+                .retrier
+                .run("unarchiving thread", || {
+                    thread
+                        .id
+                        .edit_thread(&ctx.http, EditThread::new().archived(false))
+                })
                 .await?;
         }
 
-        eprintln!("Done populating thread for {:?}", emoji);
-        Ok(())
+        let sent = self.own_messages(ctx, &thread).await?;
+        eprintln!(
+            "Updating existing thread {:?} for {:?} ({} of {} messages present, {:?})",
+            thread.id,
+            plan.name,
+            sent.len(),
+            plan.posts.len(),
+            self.options.on_existing,
+        );
+        if self.options.on_existing == OnExisting::Edit {
+            for (post, message) in plan.posts.iter().zip(&sent) {
+                self.retrier
+                    .run("editing message", || {
+                        thread.edit_message(&ctx.http, message.id, post.to_edit())
+                    })
+                    .await?;
+            }
+            for message in sent.iter().skip(plan.posts.len()) {
+                self.retrier
+                    .run("deleting message", || {
+                        thread.id.delete_message(&ctx.http, message.id)
+                    })
+                    .await?;
+            }
+        }
+
+        Ok((thread.id, sent.len().min(plan.posts.len())))
+    }
+
+    /// Find a thread with the given name that we previously created in `channel`.
+    ///
+    /// Only the 100 most recently archived threads are considered.
+    async fn find_existing_thread(
+        &self,
+        ctx: &Session,
+        channel: &GuildChannel,
+        name: &str,
+    ) -> Result<Option<GuildChannel>, SerenityError> {
+        let user_id = ctx.user_id;
+        let active = self
+            .retrier
+            .run("fetching active threads", || {
+                channel.guild_id.get_active_threads(&ctx.http)
+            })
+            .await?;
+        let archived = self
+            .retrier
+            .run("fetching archived threads", || {
+                channel
+                    .id
+                    .get_archived_public_threads(&ctx.http, None, Some(100))
+            })
+            .await?;
+        Ok(active
+            .threads
+            .into_iter()
+            .chain(archived.threads)
+            .find(|thread| {
+                thread.parent_id == Some(channel.id)
+                    && thread.owner_id == Some(user_id)
+                    && thread.name == name
+            }))
     }
 
-    async fn create_thread(&self, ctx: &Context, emoji: &Option<Emoji>) -> GuildChannel {
+    /// All regular messages we sent into `thread`, oldest first.
+    async fn own_messages(
+        &self,
+        ctx: &Session,
+        thread: &GuildChannel,
+    ) -> Result<Vec<Message>, SerenityError> {
+        let user_id = ctx.user_id;
+        let mut messages = Vec::new();
+        let mut after = MessageId::new(1);
+        loop {
+            let page = self
+                .retrier
+                .run("fetching thread messages", || {
+                    thread.messages(&ctx.http, GetMessages::new().after(after).limit(100))
+                })
+                .await?;
+            let Some(last) = page.iter().map(|msg| msg.id).max() else {
+                break;
+            };
+            after = last;
+            messages.extend(
+                page.into_iter()
+                    .filter(|msg| msg.author.id == user_id && msg.kind == MessageType::Regular),
+            );
+        }
+        messages.sort_by_key(|msg| msg.id);
+        Ok(messages)
+    }
+
+    /// Post the summary or, depending on `--on-existing`,
+    /// reuse the one posted before for the same period.
+    ///
+    /// Returns the summary message.
+    async fn post_summary(&self, ctx: &Session, state: &RunState) -> Result<MessageId, BotError> {
         let channel_id = self.config.target_channel_id();
-        let channel = channel_id
-            .to_channel(&ctx.http)
+        let post = render::render_summary(&state.title, &state.threads);
+        let existing = match self.options.on_existing {
+            OnExisting::Duplicate => None,
+            _ => {
+                self.find_existing_summary(ctx, channel_id, &state.title)
+                    .await?
+            }
+        };
+        let message = match existing {
+            Some(message) if self.options.on_existing == OnExisting::Edit => {
+                eprintln!("Editing existing summary {:?}", message.id);
+                self.retrier
+                    .run("editing summary", || {
+                        channel_id.edit_message(&ctx.http, message.id, post.to_edit())
+                    })
+                    .await?
+            }
+            Some(message) => {
+                eprintln!("Skipping existing summary {:?}", message.id);
+                return Ok(message.id);
+            }
+            None => {
+                eprintln!("Posting summary in {:?}", channel_id);
+                self.retrier
+                    .run("sending summary", || {
+                        channel_id.send_message(&ctx.http, post.to_create())
+                    })
+                    .await?
+            }
+        };
+        if self.config.summary.pin && !message.pinned {
+            self.retrier
+                .run("pinning summary", || message.pin(&ctx.http))
+                .await?;
+        }
+        Ok(message.id)
+    }
+
+    /// The summary recorded in the history for `title`, if it still exists in `channel_id`.
+    async fn find_existing_summary(
+        &self,
+        ctx: &Session,
+        channel_id: ChannelId,
+        title: &str,
+    ) -> Result<Option<Message>, BotError> {
+        let history = History::load(&self.config.history_file)?;
+        let Some(posted) = history
+            .summaries
+            .get(title)
+            .filter(|posted| posted.channel_id == channel_id)
+        else {
+            return Ok(None);
+        };
+        match self
+            .retrier
+            .run("fetching summary", || {
+                channel_id.message(&ctx.http, posted.message_id)
+            })
             .await
-            .unwrap()
-            .guild()
-            .unwrap();
-        let name = format!(
-            "{:?} - {}",
-            self.options.calendar_week,
-            emoji.as_ref().map(emoji_as_string).unwrap_or("Other"),
-        );
+            .map_err(BotError::from)
+        {
+            Ok(message) => Ok(Some(message)),
+            Err(BotError::NotFound(_)) => {
+                eprintln!("Summary {:?} no longer exists", posted.message_id);
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
 
-        eprintln!("Creating thread for {:?} in {:?}", emoji, channel_id);
-        channel
-            .create_thread(
-                &ctx.http,
-                CreateThread::new(name)
-                    .auto_archive_duration(AutoArchiveDuration::OneWeek)
-                    .kind(ChannelType::PublicThread),
-            )
+    async fn create_thread(
+        &self,
+        ctx: &Session,
+        channel: &GuildChannel,
+        name: String,
+    ) -> Result<GuildChannel, SerenityError> {
+        eprintln!("Creating thread {:?} in {:?}", name, channel.id);
+        self.retrier
+            .run("creating thread", || {
+                channel.create_thread(
+                    &ctx.http,
+                    CreateThread::new(name.clone())
+                        .auto_archive_duration(AutoArchiveDuration::OneWeek)
+                        .kind(ChannelType::PublicThread),
+                )
+            })
             .await
-            .expect("No permissions to create thread!")
     }
+}
 
-    async fn shutdown(&self, ctx: &Context) {
-        let data = ctx.data.read().await;
-        if let Some(manager) = data.get::<ShardManagerContainer>() {
-            eprintln!("Shutting down...");
-            manager.shutdown_all().await;
-        } else {
-            eprintln!("There was a problem getting the shard manager");
+/// Print the posts of a run instead of sending them.
+fn print_plan(state: &RunState) {
+    println!("Toplists for {}", state.title);
+    for thread in &state.threads {
+        println!();
+        println!(
+            "=== Thread {:?} ({} messages) ===",
+            thread.name,
+            thread.posts.len()
+        );
+        if let Some(comparison) = render::render_comparison(thread) {
+            println!("{comparison}");
+        }
+        for post in &thread.posts {
+            println!("{post}");
         }
     }
 }
 
+/// The entries of every toplist, by their label.
+fn lists_by_label(toplist: Toplist) -> HashMap<String, BTreeSet<Entry>> {
+    let mut lists: HashMap<_, _> = toplist
+        .top
+        .into_iter()
+        .map(|(emoji, list)| (emoji_as_string(&emoji).to_owned(), list))
+        .collect();
+    lists.insert(emoji_label(&None).to_owned(), toplist.other);
+    lists
+}
+
+/// Whether the `--only` value `selected` refers to the toplist labeled `label`,
+/// also by its shortcode.
+fn selects(selected: &str, label: &str) -> bool {
+    selected.eq_ignore_ascii_case(label)
+        || selected.trim_matches(':') == label
+        || emojis::get_by_shortcode(selected.trim_matches(':'))
+            .is_some_and(|found| found.as_str() == label)
+}
+
+/// Reject `--only` values matching no toplist of any profile,
+/// which would otherwise silently produce no toplists.
+fn check_only(configs: &[Config], only: &[String]) -> Result<(), BotError> {
+    let mut labels: Vec<String> = Vec::new();
+    for config in configs {
+        let emojis = config
+            .toplist
+            .iter()
+            .map(|entry| Some(entry.emoji.clone()))
+            .chain(config.other.enabled.then_some(None));
+        for emoji in emojis {
+            let label = emoji_label(&emoji);
+            if !labels.iter().any(|known| known == label) {
+                labels.push(label.to_owned());
+            }
+        }
+    }
+    for selected in only {
+        if !labels.iter().any(|label| selects(selected, label)) {
+            return Err(BotError::Config(format!(
+                "`--only {selected}` matches none of the toplists: {}",
+                labels.join(", ")
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn thread_name(period: &Period, emoji: &Option<Emoji>) -> String {
+    format!("{} - {}", period.label, emoji_label(emoji))
+}
+
+fn emoji_label(emoji: &Option<Emoji>) -> &str {
+    emoji.as_ref().map(emoji_as_string).unwrap_or("Other")
+}
+
 fn emoji_as_string(emoji: &Emoji) -> &str {
     match emoji {
         Emoji::Custom { name, .. } => name,
         Emoji::Unicode { string } => string,
+        Emoji::Named { name } => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::testing::{TestSource, CHANNEL_ID};
+    use crate::toplist::{Author, Content};
+
+    /// A counter with the configuration `config` for the fixture channel
+    /// and the command-line arguments `args`.
+    fn counter(config: &str, args: &[&str]) -> ReactionCounter {
+        let config: Config = toml::from_str(&format!(
+            r#"
+            channel_id = {CHANNEL_ID}
+            history_file = "fixtures/no-history.json"
+            {config}
+            "#
+        ))
+        .unwrap();
+        let options = Cli::try_parse_from(["reaction_toplist_bot"].iter().chain(args))
+            .unwrap()
+            .options()
+            .unwrap();
+        ReactionCounter {
+            retrier: Retrier::new(&config.retry),
+            config,
+            options,
+        }
+    }
+
+    /// A counter scanning week `2025-10` for a ⭐ toplist of three.
+    fn week_counter(slice_hours: u32) -> ReactionCounter {
+        counter(
+            &format!(
+                r#"
+                toplist = [{{ emoji.string = "⭐", max = 3 }}]
+                other = {{}}
+                scan = {{ slice_hours = {slice_hours} }}
+                "#
+            ),
+            &["scan", "2025-10"],
+        )
+    }
+
+    fn boundaries() -> TestSource {
+        TestSource::new("page-boundaries.json")
+    }
+
+    /// Check that exactly the messages of the week are fetched, oldest first.
+    async fn check_week(source: &dyn DiscordSource) {
+        let counter = week_counter(24);
+        let week = &counter.options.period;
+        let msgs = counter
+            .fetch_slice(source, CHANNEL_ID, week.start, week.end)
+            .await
+            .unwrap();
+        assert_eq!(msgs.len(), 7 * 24);
+        assert!(msgs.windows(2).all(|pair| pair[0].id < pair[1].id));
+        assert_eq!(*msgs[0].timestamp, week.start);
+        assert_eq!(
+            *msgs[msgs.len() - 1].timestamp,
+            week.end - Duration::hours(1)
+        );
+    }
+
+    /// Check that the last hours of the week top the ⭐ toplist.
+    async fn check_toplist(source: &dyn DiscordSource, slice_hours: u32) {
+        let counter = week_counter(slice_hours);
+        let periods = counter.periods();
+        let toplists = counter.scan(source, None, &periods).await.unwrap();
+        let lists = toplists.into_iter().map(lists_by_label).next().unwrap();
+        let top: Vec<_> = lists["⭐"]
+            .iter()
+            .map(|entry| (entry.count, entry.content.text.as_str()))
+            .collect();
+        assert_eq!(
+            top,
+            [
+                (20, "last hours of the week (2025-03-09T21:00:00+00:00)"),
+                (20, "last hours of the week (2025-03-09T22:00:00+00:00)"),
+                (20, "last hours of the week (2025-03-09T23:00:00+00:00)"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn pages_straddling_the_end() {
+        check_week(&boundaries()).await;
+        check_toplist(&boundaries(), 24).await;
+        // Slices that do not start at a page boundary.
+        check_toplist(&boundaries(), 5).await;
+    }
+
+    #[tokio::test]
+    async fn pages_in_mixed_order() {
+        check_week(&boundaries().mixed()).await;
+        check_toplist(&boundaries().mixed(), 24).await;
+        check_toplist(&boundaries().mixed(), 5).await;
+    }
+
+    #[tokio::test]
+    async fn weeks_without_a_thread_are_compared_with() {
+        let counter = counter(
+            r#"
+            toplist = [{ emoji.string = "⭐" }, { emoji.string = "🙃" }]
+            other = {}
+            "#,
+            &["backfill", "--from", "2025-09", "--to", "2025-10"],
+        );
+        let source = TestSource::new("week-2025-10.json");
+        let states = counter.collect(&source, None).await.unwrap();
+        let [w09, w10] = &states[..] else {
+            panic!("expected two weeks");
+        };
+
+        // 🙃 was not used in week 9, so it got no thread but is still recorded.
+        assert!(w09.threads.iter().all(|thread| thread.label != "🙃"));
+        assert_eq!(w09.stats["🙃"], Stats::default());
+        assert!(!w09.stats.contains_key("Other"));
+
+        let thread = w10
+            .threads
+            .iter()
+            .find(|thread| thread.label == "🙃")
+            .unwrap();
+        assert_eq!(thread.previous, Some(Stats::default()));
+        assert_eq!(thread.stats, Some(w10.stats["🙃"]));
+    }
+
+    #[test]
+    fn unknown_only_values_are_rejected() {
+        let config = |toplist: &str| counter(toplist, &["scan"]).config;
+        let configs = [
+            config(
+                r#"toplist = [{ emoji.string = "⭐" }]
+                other = {}"#,
+            ),
+            config(
+                r#"toplist = [{ emoji.string = "🙃" }]
+                other = { enabled = true }"#,
+            ),
+        ];
+        let only = |values: &[&str]| {
+            let values: Vec<_> = values.iter().map(|value| value.to_string()).collect();
+            check_only(&configs, &values)
+        };
+        assert!(only(&[]).is_ok());
+        assert!(only(&["⭐", ":upside_down_face:", "other"]).is_ok());
+        assert!(only(&["star"]).is_ok());
+        match only(&["⭐", "stra"]) {
+            Err(BotError::Config(msg)) => assert_eq!(
+                msg,
+                "`--only stra` matches none of the toplists: ⭐, 🙃, Other"
+            ),
+            result => panic!("unexpected {result:?}"),
+        }
+    }
+
+    /// A counter combining weeks into a ⭐ best of `max` entries.
+    fn best_of_counter(max: usize) -> ReactionCounter {
+        counter(
+            &format!(
+                r#"
+                toplist = [{{ emoji.string = "⭐", max = {max} }}]
+                other = {{}}
+                best_of = {{ eligible_rank = 2 }}
+                "#
+            ),
+            &["scan"],
+        )
+    }
+
+    /// The ⭐ toplist of a week, with entries as message ids and counts.
+    fn week(entries: &[(u64, u64)]) -> HashMap<String, BTreeSet<Entry>> {
+        let list = entries
+            .iter()
+            .map(|&(id, count)| Entry {
+                count,
+                id: MessageId::new(id),
+                channel_id: CHANNEL_ID,
+                guild_id: None,
+                author: Author {
+                    id: UserId::new(2000000000000000001),
+                    name: "alice".to_owned(),
+                },
+                reactions: Vec::new(),
+                content: Content::default(),
+            })
+            .collect();
+        HashMap::from([("⭐".to_owned(), list)])
+    }
+
+    fn ids(lists: &HashMap<String, BTreeSet<Entry>>) -> Vec<(u64, u64)> {
+        lists["⭐"]
+            .iter()
+            .rev()
+            .map(|entry| (entry.id.get(), entry.count))
+            .collect()
+    }
+
+    #[test]
+    fn tied_entries_share_an_eligible_rank() {
+        let best = best_of_counter(10).best_of(vec![week(&[(1, 5), (2, 4), (3, 4), (4, 3)])]);
+        assert_eq!(ids(&best), [(1, 5), (3, 4), (2, 4)]);
+    }
+
+    #[test]
+    fn entries_ranked_in_two_weeks_are_counted_once() {
+        let weeks = || vec![week(&[(2, 6), (3, 2)]), week(&[(1, 5), (2, 4)])];
+        let best = best_of_counter(10).best_of(weeks());
+        assert_eq!(ids(&best), [(2, 6), (1, 5), (3, 2)]);
+
+        let best = best_of_counter(2).best_of(weeks());
+        assert_eq!(ids(&best), [(2, 6), (1, 5)]);
     }
 }
@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serenity::http::Http;
+use serenity::model::id::{ChannelId, UserId};
+use serenity::model::permissions::Permissions;
+
+use crate::config::Config;
+use crate::error::BotError;
+use crate::retry::Retrier;
+
+const SOURCE_PERMISSIONS: Permissions =
+    Permissions::VIEW_CHANNEL.union(Permissions::READ_MESSAGE_HISTORY);
+
+const TARGET_PERMISSIONS: Permissions = Permissions::VIEW_CHANNEL
+    .union(Permissions::CREATE_PUBLIC_THREADS)
+    .union(Permissions::SEND_MESSAGES_IN_THREADS)
+    .union(Permissions::EMBED_LINKS)
+    .union(Permissions::USE_EXTERNAL_EMOJIS);
+
+/// Which channels a run is going to use.
+#[derive(Debug, Clone, Copy)]
+pub struct Needs {
+    /// Scanning the source channel.
+    pub source: bool,
+    /// Posting into the target channel.
+    pub target: bool,
+    /// Reading the messages of existing threads and the summary posted before.
+    pub reuse_threads: bool,
+}
+
+impl Needs {
+    pub const ALL: Needs = Needs {
+        source: true,
+        target: true,
+        reuse_threads: true,
+    };
+}
+
+/// Check that we have every permission needed for the run
+/// before spending time on scanning,
+/// reporting all missing permissions at once.
+pub async fn check_permissions(
+    config: &Config,
+    retrier: &Retrier,
+    http: &Http,
+    user_id: UserId,
+    needs: Needs,
+) -> Result<(), BotError> {
+    let problems =
+        missing_permissions(config, retrier, http, user_id, needs, &BTreeSet::new()).await?;
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(BotError::Permission(problems.join("; ")))
+    }
+}
+
+/// The missing permissions in every channel needed for the run,
+/// except those in `skip`, e.g. because they are known not to exist.
+pub async fn missing_permissions(
+    config: &Config,
+    retrier: &Retrier,
+    http: &Http,
+    user_id: UserId,
+    needs: Needs,
+    skip: &BTreeSet<ChannelId>,
+) -> Result<Vec<String>, BotError> {
+    let mut target_permissions = TARGET_PERMISSIONS;
+    if needs.reuse_threads {
+        target_permissions |= Permissions::READ_MESSAGE_HISTORY;
+    }
+    let mut summary_permissions = Permissions::empty();
+    if config.summary.enabled {
+        summary_permissions |= Permissions::SEND_MESSAGES;
+        if needs.reuse_threads {
+            summary_permissions |= Permissions::READ_MESSAGE_HISTORY;
+        }
+    }
+    if config.summary.pin {
+        summary_permissions |= Permissions::MANAGE_MESSAGES;
+    }
+
+    let mut required: BTreeMap<ChannelId, Permissions> = BTreeMap::new();
+    for channels in config.toplist_channels() {
+        if needs.source {
+            *required.entry(config.source_channel(channels)).or_default() |= SOURCE_PERMISSIONS;
+        }
+        if needs.target {
+            *required.entry(config.target_channel(channels)).or_default() |= target_permissions;
+        }
+    }
+    if needs.target && !summary_permissions.is_empty() {
+        *required.entry(config.target_channel_id()).or_default() |= summary_permissions;
+    }
+
+    let mut problems = Vec::new();
+    for (channel_id, permissions) in required {
+        if skip.contains(&channel_id) {
+            continue;
+        }
+        let channel = match retrier
+            .run("fetching channel", || channel_id.to_channel(http))
+            .await
+            .map_err(BotError::from)
+        {
+            Ok(channel) => channel,
+            Err(BotError::Permission(err)) => {
+                problems.push(format!("{channel_id} is not accessible ({err})"));
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let channel = channel
+            .guild()
+            .ok_or_else(|| BotError::Config(format!("{channel_id} is not a guild channel")))?;
+        let guild = retrier
+            .run("fetching guild", || channel.guild_id.to_partial_guild(http))
+            .await?;
+        let member = retrier
+            .run("fetching own member", || {
+                channel.guild_id.member(http, user_id)
+            })
+            .await?;
+
+        let missing = permissions - guild.user_permissions_in(&channel, &member);
+        if missing.is_empty() {
+            eprintln!("Permissions in #{} ({}) are fine", channel.name, channel_id);
+        } else {
+            problems.push(format!(
+                "#{} ({}) is missing {}",
+                channel.name,
+                channel_id,
+                missing.get_permission_names().join(", "),
+            ));
+        }
+    }
+
+    Ok(problems)
+}
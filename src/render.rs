@@ -0,0 +1,458 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use serenity::all::{
+    CreateAllowedMentions, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, CreateMessage,
+    EditMessage, Mentionable,
+};
+
+use crate::config::Layout;
+use crate::state::ThreadState;
+use crate::toplist::{Entry, Stats};
+
+// https://discord.com/developers/docs/resources/message#embed-object-embed-limits
+const MAX_EMBEDS_PER_MESSAGE: usize = 10;
+const MAX_EMBED_CHARS_PER_MESSAGE: usize = 6000;
+const MAX_EMBED_TITLE: usize = 256;
+const MAX_EMBED_DESCRIPTION: usize = 4096;
+const MAX_EMBED_FOOTER: usize = 2048;
+
+/// A single message to be sent into a toplist thread.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Post {
+    pub content: String,
+    pub embeds: Vec<Embed>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Embed {
+    pub title: String,
+    pub description: String,
+    pub url: Option<String>,
+    pub author: Option<String>,
+    pub image: Option<String>,
+    pub footer: Option<String>,
+}
+
+impl Post {
+    pub fn to_create(&self) -> CreateMessage {
+        let mut builder = CreateMessage::new()
+            .embeds(self.embeds.iter().map(Embed::to_create).collect())
+            .allowed_mentions(CreateAllowedMentions::new());
+        if !self.content.is_empty() {
+            builder = builder.content(&self.content);
+        }
+        builder
+    }
+
+    pub fn to_edit(&self) -> EditMessage {
+        EditMessage::new()
+            .content(&self.content)
+            .embeds(self.embeds.iter().map(Embed::to_create).collect())
+            .allowed_mentions(CreateAllowedMentions::new())
+    }
+}
+
+impl fmt::Display for Post {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.content.is_empty() {
+            writeln!(f, "{}", self.content)?;
+        }
+        for embed in &self.embeds {
+            if !embed.title.trim().is_empty() {
+                writeln!(f, "[{}]", embed.title)?;
+            }
+            if let Some(author) = &embed.author {
+                writeln!(f, "  by {author}")?;
+            }
+            for line in embed.description.lines() {
+                writeln!(f, "  {line}")?;
+            }
+            if let Some(footer) = &embed.footer {
+                writeln!(f, "  -- {footer}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Embed {
+    pub fn to_create(&self) -> CreateEmbed {
+        let mut builder = CreateEmbed::new()
+            .title(&self.title)
+            .description(&self.description);
+        if let Some(url) = &self.url {
+            builder = builder.url(url);
+        }
+        if let Some(author) = &self.author {
+            builder = builder.author(CreateEmbedAuthor::new(author));
+        }
+        if let Some(image) = &self.image {
+            builder = builder.image(image);
+        }
+        if let Some(footer) = &self.footer {
+            builder = builder.footer(CreateEmbedFooter::new(footer));
+        }
+        builder
+    }
+
+    /// Number of characters counting towards the per-message embed limit.
+    fn char_count(&self) -> usize {
+        [
+            Some(&self.title),
+            Some(&self.description),
+            self.author.as_ref(),
+            self.footer.as_ref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|s| s.chars().count())
+        .sum()
+    }
+}
+
+/// The top entry of a toplist, as listed in the summary message.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Winner {
+    pub count: u64,
+    pub link: String,
+    pub author: String,
+}
+
+impl From<&Entry> for Winner {
+    fn from(item: &Entry) -> Self {
+        Winner {
+            count: item.count,
+            link: item.link(),
+            author: item.author.id.mention().to_string(),
+        }
+    }
+}
+
+/// Render a toplist into the messages to send, in posting order.
+///
+/// Entries are posted from the lowest to the highest rank
+/// so that the winner ends up at the bottom of the thread.
+pub fn render_toplist(list: &BTreeSet<Entry>, layout: Layout) -> Vec<Post> {
+    let ranked = ranked(list);
+    match layout {
+        Layout::Classic => ranked
+            .into_iter()
+            .flat_map(|(item, rank)| classic_entry(item, rank))
+            .collect(),
+        Layout::Compact => pack_embeds(
+            ranked
+                .into_iter()
+                .map(|(item, rank)| compact_entry(item, rank)),
+        ),
+    }
+}
+
+/// Assign ranks to the entries (sharing a rank on equal counts)
+/// and return them from the lowest to the highest rank.
+fn ranked(list: &BTreeSet<Entry>) -> Vec<(&Entry, usize)> {
+    let mut items_with_rank: Vec<_> = list
+        .iter()
+        .rev()
+        .enumerate()
+        .scan((0, 0), |(rank, count), (i, item)| {
+            if *count != item.count {
+                *rank = i + 1;
+            }
+            *count = item.count;
+            Some((item, *rank))
+        })
+        .collect();
+    items_with_rank.reverse();
+    items_with_rank
+}
+
+fn classic_entry(item: &Entry, rank: usize) -> [Post; 3] {
+    [
+        Post {
+            content: format!(
+                "```c\n{} // {} user{}\n```",
+                rank,
+                item.count,
+                if item.count == 1 { "" } else { "s" },
+            ),
+            ..Default::default()
+        },
+        Post {
+            content: item.content.joined(),
+            ..Default::default()
+        },
+        Post {
+            content: format!("by {} ({})", item.author.id.mention(), item.author.name),
+            embeds: vec![Embed {
+                title: "  ".to_owned(),
+                description: format!("{} | [link]({})", reactions_line(item), item.link()),
+                ..Default::default()
+            }],
+        },
+    ]
+}
+
+fn compact_entry(item: &Entry, rank: usize) -> Embed {
+    let image = item.content.first_image().map(|t| t.url.clone());
+    let lines = std::iter::once(item.content.text.clone())
+        .filter(|text| !text.is_empty())
+        .chain(item.content.other_attachments().map(|t| t.url.clone()));
+    let description = itertools::Itertools::intersperse(lines, "\n".to_owned()).collect::<String>();
+
+    let mut embed = Embed {
+        title: truncate(
+            &format!(
+                "#{} // {} user{}",
+                rank,
+                item.count,
+                if item.count == 1 { "" } else { "s" },
+            ),
+            MAX_EMBED_TITLE,
+        ),
+        description: String::new(),
+        url: Some(item.link()),
+        author: Some(item.author.name.clone()),
+        image,
+        footer: Some(truncate(&reactions_line(item), MAX_EMBED_FOOTER)),
+    };
+    // Even alone, the embed must not exceed the limit of the whole message.
+    let max_description =
+        MAX_EMBED_DESCRIPTION.min(MAX_EMBED_CHARS_PER_MESSAGE.saturating_sub(embed.char_count()));
+    embed.description = truncate(
+        &format!("{}\n\nby {}", description, item.author.id.mention()),
+        max_description,
+    );
+    embed
+}
+
+fn reactions_line(item: &Entry) -> String {
+    let reaction_strs: Vec<_> = item
+        .reactions
+        .iter()
+        .map(|r| format!("{} {}", r.reaction, r.count))
+        .collect();
+    reaction_strs.join(" | ")
+}
+
+/// Render the summary of all toplists, to be posted in the parent channel.
+pub fn render_summary(title: &str, threads: &[ThreadState]) -> Post {
+    let lines: Vec<_> = threads
+        .iter()
+        .filter_map(|thread| {
+            let thread_link = thread.thread_id?.mention();
+            let line = match &thread.winner {
+                Some(winner) => format!(
+                    "**{}** · {} user{} · {} · [winner]({}) by {}",
+                    thread.label,
+                    winner.count,
+                    if winner.count == 1 { "" } else { "s" },
+                    thread_link,
+                    winner.link,
+                    winner.author,
+                ),
+                None => format!("**{}** · {}", thread.label, thread_link),
+            };
+            Some(match render_comparison(thread) {
+                Some(comparison) => format!("{line}\n{comparison}"),
+                None => line,
+            })
+        })
+        .collect();
+
+    Post {
+        embeds: vec![Embed {
+            title: truncate(&format!("Toplists for {}", title), MAX_EMBED_TITLE),
+            description: truncate(&lines.join("\n"), MAX_EMBED_DESCRIPTION),
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+/// The activity of a toplist, compared to the previous week if known,
+/// e.g. "34 reactions ▲5 · 12 posts ▼2 · 9 authors = · winner 7 ▲1".
+pub fn render_comparison(thread: &ThreadState) -> Option<String> {
+    let stats = thread.stats?;
+    let change = |value: fn(&Stats) -> u64| {
+        let now = value(&stats);
+        match thread.previous.as_ref().map(value) {
+            Some(before) if now > before => format!(" ▲{}", now - before),
+            Some(before) if now < before => format!(" ▼{}", before - now),
+            Some(_) => " =".to_owned(),
+            None => String::new(),
+        }
+    };
+    let plural = |count: u64| if count == 1 { "" } else { "s" };
+    Some(format!(
+        "{} reaction{}{} · {} post{}{} · {} author{}{} · winner {}{}",
+        stats.reactions,
+        plural(stats.reactions),
+        change(|s| s.reactions),
+        stats.posts,
+        plural(stats.posts),
+        change(|s| s.posts),
+        stats.authors,
+        plural(stats.authors),
+        change(|s| s.authors),
+        stats.winner,
+        change(|s| s.winner),
+    ))
+}
+
+/// Group embeds into as few posts as possible without exceeding Discord's limits.
+fn pack_embeds(embeds: impl IntoIterator<Item = Embed>) -> Vec<Post> {
+    let mut posts: Vec<Post> = Vec::new();
+    let mut chars = 0;
+    for embed in embeds {
+        let embed_chars = embed.char_count();
+        let fits = posts.last().is_some_and(|post| {
+            post.embeds.len() < MAX_EMBEDS_PER_MESSAGE
+                && chars + embed_chars <= MAX_EMBED_CHARS_PER_MESSAGE
+        });
+        if !fits {
+            posts.push(Post::default());
+            chars = 0;
+        }
+        chars += embed_chars;
+        posts.last_mut().unwrap().embeds.push(embed);
+    }
+    posts
+}
+
+fn truncate(s: &str, max_chars: usize) -> String {
+    match s.char_indices().nth(max_chars.saturating_sub(1)) {
+        Some((i, _)) if s[i..].chars().count() > 1 => format!("{}…", &s[..i]),
+        _ => s.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::model::channel::ReactionType;
+    use serenity::model::id::{AttachmentId, ChannelId, MessageId, UserId};
+
+    use super::*;
+    use crate::toplist::{Attachment, Author, Content, ReactionCount};
+
+    fn entry(id: u64, count: u64, text: &str) -> Entry {
+        Entry {
+            count,
+            id: MessageId::new(id),
+            channel_id: ChannelId::new(1),
+            guild_id: None,
+            author: Author {
+                id: UserId::new(2),
+                name: "alice".to_owned(),
+            },
+            reactions: vec![ReactionCount {
+                reaction: ReactionType::Unicode("⭐".to_owned()),
+                count,
+            }],
+            content: Content {
+                text: text.to_owned(),
+                attachments: Vec::new(),
+            },
+        }
+    }
+
+    fn list() -> BTreeSet<Entry> {
+        [
+            entry(1, 4, "First of two with four stars"),
+            entry(2, 4, "Second of two with four stars"),
+            entry(3, 2, "Two stars"),
+            entry(4, 1, ""),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn rendered(posts: &[Post]) -> String {
+        posts.iter().map(|post| format!("{post}---\n")).collect()
+    }
+
+    #[test]
+    fn equal_counts_share_a_rank() {
+        let list = list();
+        let ranks: Vec<_> = ranked(&list)
+            .into_iter()
+            .map(|(item, rank)| (item.id.get(), rank))
+            .collect();
+        assert_eq!(ranks, [(4, 4), (3, 3), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn classic_layout() {
+        let posts = render_toplist(&list(), Layout::Classic);
+        assert_eq!(posts.len(), 12);
+        assert_eq!(
+            rendered(&posts[6..]),
+            "```c\n1 // 4 users\n```\n---\n\
+             First of two with four stars\n---\n\
+             by <@2> (alice)\n  ⭐ 4 | [link](https://discord.com/channels/@me/1/1)\n---\n\
+             ```c\n1 // 4 users\n```\n---\n\
+             Second of two with four stars\n---\n\
+             by <@2> (alice)\n  ⭐ 4 | [link](https://discord.com/channels/@me/1/2)\n---\n"
+        );
+        assert_eq!(posts[0].content, "```c\n4 // 1 user\n```");
+        assert_eq!(posts[3].content, "```c\n3 // 2 users\n```");
+    }
+
+    #[test]
+    fn compact_layout() {
+        let mut list = list();
+        let mut last = list.pop_first().unwrap();
+        last.content.attachments = vec![Attachment {
+            id: AttachmentId::new(5),
+            url: "https://cdn.example.com/image.png".to_owned(),
+            content_type: Some("image/png".to_owned()),
+        }];
+        list.insert(last);
+
+        let posts = render_toplist(&list, Layout::Compact);
+        assert_eq!(posts.len(), 1);
+        assert_eq!(
+            rendered(&posts),
+            "[#4 // 1 user]\n  by alice\n  \n  \n  by <@2>\n  -- ⭐ 1\n\
+             [#3 // 2 users]\n  by alice\n  Two stars\n  \n  by <@2>\n  -- ⭐ 2\n\
+             [#1 // 4 users]\n  by alice\n  First of two with four stars\n  \n  by <@2>\n  -- ⭐ 4\n\
+             [#1 // 4 users]\n  by alice\n  Second of two with four stars\n  \n  by <@2>\n  -- ⭐ 4\n\
+             ---\n"
+        );
+        let embeds = &posts[0].embeds;
+        assert_eq!(
+            embeds[0].image.as_deref(),
+            Some("https://cdn.example.com/image.png")
+        );
+        assert_eq!(
+            embeds[3].url.as_deref(),
+            Some("https://discord.com/channels/@me/1/2")
+        );
+    }
+
+    #[test]
+    fn compact_entry_fits_into_one_message() {
+        let mut item = entry(1, 3, &"a".repeat(5000));
+        item.author.name = "b".repeat(100);
+        item.content.attachments = (0..100)
+            .map(|i| Attachment {
+                id: AttachmentId::new(i + 1),
+                url: format!("https://cdn.example.com/{}", "c".repeat(40)),
+                content_type: None,
+            })
+            .collect();
+        item.reactions = (0..200)
+            .map(|i| ReactionCount {
+                reaction: ReactionType::Unicode("⭐".to_owned()),
+                count: i,
+            })
+            .collect();
+
+        let embed = compact_entry(&item, 1);
+        assert!(embed.footer.as_ref().unwrap().chars().count() <= MAX_EMBED_FOOTER);
+        assert!(embed.description.chars().count() <= MAX_EMBED_DESCRIPTION);
+        assert!(embed.char_count() <= MAX_EMBED_CHARS_PER_MESSAGE);
+        assert!(embed.description.ends_with('…'));
+    }
+}
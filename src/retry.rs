@@ -0,0 +1,207 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use serenity::http::HttpError;
+use serenity::prelude::SerenityError;
+
+use crate::config::Retry;
+
+/// Retries Discord requests that failed for transient reasons
+/// with exponential backoff.
+///
+/// Besides the number of attempts per request,
+/// the total number of retries during a run is limited by a shared budget.
+#[derive(Debug)]
+pub struct Retrier {
+    config: Retry,
+    budget: AtomicU32,
+}
+
+impl Retrier {
+    pub fn new(config: &Retry) -> Self {
+        Retrier {
+            config: config.clone(),
+            budget: AtomicU32::new(config.budget),
+        }
+    }
+
+    /// Run `f` until it succeeds, fails permanently,
+    /// or the attempts or the retry budget are exhausted.
+    ///
+    /// Note that retrying a request that creates something (e.g. a message)
+    /// may create it twice if Discord processed it despite responding with an error.
+    pub async fn run<T, F, Fut>(&self, what: &str, mut f: F) -> Result<T, SerenityError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, SerenityError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let err = match f().await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            if !is_transient(&err) {
+                eprintln!("Permanent error while {what}: {err}");
+                return Err(err);
+            }
+            if attempt >= self.config.max_attempts {
+                eprintln!("Giving up on {what} after {attempt} attempts: {err}");
+                return Err(err);
+            }
+            if !self.take_from_budget() {
+                eprintln!("Retry budget exhausted, giving up on {what}: {err}");
+                return Err(err);
+            }
+
+            let delay = self.delay(attempt);
+            eprintln!(
+                "Transient error while {what} (attempt {attempt}/{}), retrying in {delay:?}: {err}",
+                self.config.max_attempts
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    fn take_from_budget(&self) -> bool {
+        self.budget
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| {
+                left.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt - 1);
+        let millis = self.config.base_delay_ms.saturating_mul(factor);
+        Duration::from_millis(millis.min(self.config.max_delay_ms))
+    }
+}
+
+/// Whether the error is worth retrying,
+/// i.e. a server-side error or a network failure.
+///
+/// Client errors such as missing permissions or unknown messages are permanent.
+/// Rate limits are already handled by serenity's ratelimiter.
+pub fn is_transient(err: &SerenityError) -> bool {
+    match err {
+        SerenityError::Http(HttpError::UnsuccessfulRequest(response)) => {
+            response.status_code.is_server_error()
+        }
+        SerenityError::Http(HttpError::Request(_)) => true,
+        SerenityError::Io(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::http::ErrorResponse;
+
+    use super::*;
+
+    fn retrier(budget: u32) -> Retrier {
+        Retrier::new(&Retry {
+            max_attempts: 3,
+            budget,
+            base_delay_ms: 1,
+            max_delay_ms: 5,
+        })
+    }
+
+    async fn response(status: u16) -> SerenityError {
+        let response = http::Response::builder()
+            .status(status)
+            .body(r#"{"code": 0, "message": "test"}"#)
+            .unwrap();
+        let response = ErrorResponse::from_response(response.into(), reqwest::Method::GET).await;
+        SerenityError::Http(HttpError::UnsuccessfulRequest(response))
+    }
+
+    fn network() -> SerenityError {
+        SerenityError::Io(std::io::Error::from(std::io::ErrorKind::ConnectionReset))
+    }
+
+    #[tokio::test]
+    async fn server_and_network_errors_are_transient() {
+        assert!(is_transient(&response(500).await));
+        assert!(is_transient(&response(503).await));
+        assert!(is_transient(&network()));
+        assert!(!is_transient(&response(403).await));
+        assert!(!is_transient(&response(404).await));
+        assert!(!is_transient(&SerenityError::Other("invalid")));
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_maximum() {
+        let retrier = Retrier::new(&Retry {
+            base_delay_ms: 1000,
+            max_delay_ms: 30_000,
+            ..Retry::default()
+        });
+        let delays: Vec<_> = (1..=7)
+            .map(|attempt| retrier.delay(attempt).as_secs())
+            .collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 30, 30]);
+        assert_eq!(retrier.delay(100), Duration::from_secs(30));
+    }
+
+    /// Run a request failing with `err` the first `failures` times,
+    /// returning the result and the number of attempts.
+    async fn attempt(
+        retrier: &Retrier,
+        failures: u32,
+        err: fn() -> SerenityError,
+    ) -> (Result<u32, SerenityError>, u32) {
+        let mut attempts = 0;
+        let result = retrier
+            .run("testing", || {
+                attempts += 1;
+                let result = if attempts <= failures {
+                    Err(err())
+                } else {
+                    Ok(attempts)
+                };
+                std::future::ready(result)
+            })
+            .await;
+        (result, attempts)
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let retrier = retrier(10);
+        let (result, attempts) = attempt(&retrier, 2, network).await;
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(attempts, 3);
+
+        // Giving up after `max_attempts`.
+        let (result, attempts) = attempt(&retrier, 5, network).await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        // Permanent errors are not retried.
+        let (result, attempts) = attempt(&retrier, 5, || SerenityError::Other("invalid")).await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn budget_is_shared() {
+        let retrier = retrier(3);
+        let (result, attempts) = attempt(&retrier, 2, network).await;
+        assert!(result.is_ok());
+        assert_eq!(attempts, 3);
+
+        // One retry left for all later requests.
+        let (result, attempts) = attempt(&retrier, 2, network).await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 2);
+        let (result, attempts) = attempt(&retrier, 1, network).await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+        assert!(attempt(&retrier, 0, network).await.0.is_ok());
+    }
+}
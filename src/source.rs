@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serenity::async_trait;
+use serenity::builder::GetMessages;
+use serenity::http::Http;
+use serenity::model::channel::{Message, ReactionType};
+use serenity::model::id::{ChannelId, EmojiId, MessageId, UserId};
+use serenity::model::user::User;
+use serenity::prelude::SerenityError;
+
+use crate::error::BotError;
+
+/// The Discord requests needed to build toplists,
+/// so that they can be served from somewhere else than the API.
+#[async_trait]
+pub trait DiscordSource: std::fmt::Debug + Send + Sync {
+    /// Up to `limit` messages of `channel_id` directly after `after`,
+    /// newest first like Discord returns them.
+    async fn messages_after(
+        &self,
+        channel_id: ChannelId,
+        after: MessageId,
+        limit: u8,
+    ) -> Result<Vec<Message>, SerenityError>;
+
+    async fn message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<Message, SerenityError>;
+
+    /// Up to `limit` users that reacted to a message with `reaction`.
+    async fn reaction_users(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reaction: ReactionType,
+        limit: u8,
+    ) -> Result<Vec<UserId>, SerenityError>;
+}
+
+/// Requests the Discord API.
+#[derive(Debug)]
+pub struct HttpSource {
+    http: Arc<Http>,
+}
+
+impl HttpSource {
+    pub fn new(http: Arc<Http>) -> Self {
+        HttpSource { http }
+    }
+}
+
+#[async_trait]
+impl DiscordSource for HttpSource {
+    async fn messages_after(
+        &self,
+        channel_id: ChannelId,
+        after: MessageId,
+        limit: u8,
+    ) -> Result<Vec<Message>, SerenityError> {
+        channel_id
+            .messages(&self.http, GetMessages::new().after(after).limit(limit))
+            .await
+    }
+
+    async fn message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<Message, SerenityError> {
+        self.http.get_message(channel_id, message_id).await
+    }
+
+    async fn reaction_users(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+        reaction: ReactionType,
+        limit: u8,
+    ) -> Result<Vec<UserId>, SerenityError> {
+        let users = self
+            .http
+            .get_reaction_users(channel_id, message_id, &reaction, limit, None)
+            .await?;
+        Ok(users.into_iter().map(|user| user.id).collect())
+    }
+}
+
+/// Serves messages and reactions from memory, e.g. loaded from a fixture file.
+#[derive(Debug, Default)]
+pub struct MemorySource {
+    /// Sorted by ID.
+    messages: Vec<Message>,
+    /// Users per message and reaction, keyed by the reaction as Discord formats it
+    /// (the emoji itself or `<:name:id>`).
+    reaction_users: HashMap<MessageId, HashMap<String, Vec<UserId>>>,
+}
+
+/// The fixture file format:
+/// messages as returned by the Discord API
+/// and the users that reacted to them.
+#[derive(Deserialize)]
+struct Fixture {
+    messages: Vec<Message>,
+    #[serde(default)]
+    reaction_users: HashMap<MessageId, HashMap<String, Vec<User>>>,
+}
+
+impl MemorySource {
+    pub fn new(messages: Vec<Message>) -> Self {
+        let mut source = MemorySource::default();
+        for message in messages {
+            source.add_message(message);
+        }
+        source
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self, BotError> {
+        let contents = std::fs::read_to_string(path)?;
+        let fixture: Fixture = serde_json::from_str(&contents).map_err(|err| {
+            BotError::Config(format!("invalid fixture {}: {err}", path.display()))
+        })?;
+        let mut source = MemorySource::new(fixture.messages);
+        for (message_id, reactions) in fixture.reaction_users {
+            for (reaction, users) in reactions {
+                source.set_reaction_users(message_id, reaction, users.iter().map(|u| u.id));
+            }
+        }
+        Ok(source)
+    }
+
+    /// The custom emoji used in the reactions, by name.
+    pub fn custom_emojis(&self) -> HashMap<String, EmojiId> {
+        self.messages
+            .iter()
+            .flat_map(|message| &message.reactions)
+            .filter_map(|reaction| match &reaction.reaction_type {
+                ReactionType::Custom {
+                    name: Some(name),
+                    id,
+                    ..
+                } => Some((name.clone(), *id)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn add_message(&mut self, message: Message) {
+        let i = self.messages.partition_point(|m| m.id < message.id);
+        self.messages.insert(i, message);
+    }
+
+    pub fn set_reaction_users(
+        &mut self,
+        message_id: MessageId,
+        reaction: String,
+        users: impl IntoIterator<Item = UserId>,
+    ) {
+        self.reaction_users
+            .entry(message_id)
+            .or_default()
+            .insert(reaction, users.into_iter().collect());
+    }
+}
+
+#[async_trait]
+impl DiscordSource for MemorySource {
+    async fn messages_after(
+        &self,
+        channel_id: ChannelId,
+        after: MessageId,
+        limit: u8,
+    ) -> Result<Vec<Message>, SerenityError> {
+        let mut page: Vec<_> = self
+            .messages
+            .iter()
+            .filter(|m| m.channel_id == channel_id && m.id > after)
+            .take(limit.into())
+            .cloned()
+            .collect();
+        page.reverse();
+        Ok(page)
+    }
+
+    async fn message(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<Message, SerenityError> {
+        self.messages
+            .iter()
+            .find(|m| m.channel_id == channel_id && m.id == message_id)
+            .cloned()
+            .ok_or(SerenityError::Other("message not in fixture"))
+    }
+
+    async fn reaction_users(
+        &self,
+        _channel_id: ChannelId,
+        message_id: MessageId,
+        reaction: ReactionType,
+        limit: u8,
+    ) -> Result<Vec<UserId>, SerenityError> {
+        let users = self
+            .reaction_users
+            .get(&message_id)
+            .and_then(|reactions| reactions.get(&reaction.to_string()));
+        Ok(users
+            .into_iter()
+            .flatten()
+            .take(limit.into())
+            .copied()
+            .collect())
+    }
+}
+
+/// A source and the ids shared by the tests reading the fixtures.
+#[cfg(test)]
+pub mod testing {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    /// The channel of the messages in the fixtures.
+    pub const CHANNEL_ID: ChannelId = ChannelId::new(1000000000000000001);
+    /// The bot's own user, whose reactions are not counted.
+    pub const BOT_USER_ID: UserId = UserId::new(100000000000000001);
+
+    /// Serves a fixture, optionally with each page in a mixed order,
+    /// and remembers the messages whose reaction users were requested.
+    #[derive(Debug)]
+    pub struct TestSource {
+        inner: MemorySource,
+        mixed: bool,
+        pub requested: Mutex<Vec<MessageId>>,
+    }
+
+    impl TestSource {
+        pub fn new(fixture: &str) -> Self {
+            TestSource {
+                inner: MemorySource::from_path(&Path::new("fixtures").join(fixture)).unwrap(),
+                mixed: false,
+                requested: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Serve the pages with the messages after the end of a slice
+        /// in the middle of the page.
+        pub fn mixed(mut self) -> Self {
+            self.mixed = true;
+            self
+        }
+
+        /// All messages of the fixture channel, oldest first.
+        pub async fn all_messages(&self) -> Vec<Message> {
+            let mut messages = self
+                .inner
+                .messages_after(CHANNEL_ID, MessageId::new(1), 100)
+                .await
+                .unwrap();
+            messages.reverse();
+            messages
+        }
+    }
+
+    #[async_trait]
+    impl DiscordSource for TestSource {
+        async fn messages_after(
+            &self,
+            channel_id: ChannelId,
+            after: MessageId,
+            limit: u8,
+        ) -> Result<Vec<Message>, SerenityError> {
+            let mut page = self.inner.messages_after(channel_id, after, limit).await?;
+            if self.mixed {
+                let middle = page.len() / 2;
+                page.rotate_left(middle);
+            }
+            Ok(page)
+        }
+
+        async fn message(
+            &self,
+            channel_id: ChannelId,
+            message_id: MessageId,
+        ) -> Result<Message, SerenityError> {
+            self.inner.message(channel_id, message_id).await
+        }
+
+        async fn reaction_users(
+            &self,
+            channel_id: ChannelId,
+            message_id: MessageId,
+            reaction: ReactionType,
+            limit: u8,
+        ) -> Result<Vec<UserId>, SerenityError> {
+            self.requested.lock().unwrap().push(message_id);
+            self.inner
+                .reaction_users(channel_id, message_id, reaction, limit)
+                .await
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::CHANNEL_ID;
+    use super::*;
+
+    #[tokio::test]
+    async fn pages_like_discord() {
+        let source = MemorySource::from_path(Path::new("fixtures/week-2025-10.json")).unwrap();
+        let first = source
+            .messages_after(CHANNEL_ID, MessageId::new(1), 4)
+            .await
+            .unwrap();
+        let contents: Vec<_> = first.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            [
+                "Wednesday evening",
+                "Tuesday noon",
+                "Monday morning",
+                "Before the week"
+            ]
+        );
+
+        let rest = source
+            .messages_after(CHANNEL_ID, first[0].id, 100)
+            .await
+            .unwrap();
+        let contents: Vec<_> = rest.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["After the week", "Friday night"]);
+
+        let other_channel = source
+            .messages_after(ChannelId::new(1), MessageId::new(1), 100)
+            .await
+            .unwrap();
+        assert!(other_channel.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reaction_users_up_to_limit() {
+        let source = MemorySource::from_path(Path::new("fixtures/week-2025-10.json")).unwrap();
+        let message_id = MessageId::new(1346452114636800000);
+        let star = ReactionType::Unicode("⭐".to_owned());
+        let users = source
+            .reaction_users(CHANNEL_ID, message_id, star.clone(), 3)
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 3);
+        let users = source
+            .reaction_users(CHANNEL_ID, message_id, star, 100)
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 5);
+
+        let unknown = ReactionType::Unicode("🙃".to_owned());
+        let users = source
+            .reaction_users(CHANNEL_ID, message_id, unknown, 100)
+            .await
+            .unwrap();
+        assert!(users.is_empty());
+    }
+}
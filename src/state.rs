@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, MessageId};
+
+use crate::error::BotError;
+use crate::file_utils;
+use crate::render::{Post, Winner};
+use crate::toplist::{Entry, Stats};
+
+/// Posting progress of a run, checkpointed to disk after every sent message
+/// so that an interrupted run can be resumed.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct RunState {
+    /// Title of the period the toplists were collected for.
+    pub title: String,
+    /// The ISO week of the toplists, if they are for a single week,
+    /// under which they are recorded in the history once posted.
+    #[serde(default)]
+    pub week: Option<String>,
+    pub threads: Vec<ThreadState>,
+    /// The activity during the period on every selected toplist by label,
+    /// including those without a thread, to compare the next week with.
+    #[serde(default)]
+    pub stats: BTreeMap<String, Stats>,
+    /// Whether the summary message still needs to be posted.
+    pub summary_pending: bool,
+    /// The summary message, once it has been posted or found.
+    #[serde(default)]
+    pub summary_id: Option<MessageId>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ThreadState {
+    pub label: String,
+    pub name: String,
+    pub winner: Option<Winner>,
+    pub posts: Vec<Post>,
+    /// The ranked entries the posts were rendered from, highest first.
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+    /// The activity during the period, if it was scanned.
+    #[serde(default)]
+    pub stats: Option<Stats>,
+    /// The activity during the previous week, if known, to compare with.
+    #[serde(default)]
+    pub previous: Option<Stats>,
+    /// The channel to create the thread in,
+    /// or `None` for the target channel of the configuration.
+    #[serde(default)]
+    pub channel_id: Option<ChannelId>,
+    /// The thread the posts are sent to, once it has been created or found.
+    pub thread_id: Option<ChannelId>,
+    /// Number of posts that have already been sent.
+    pub sent: usize,
+}
+
+impl ThreadState {
+    pub fn is_done(&self) -> bool {
+        self.thread_id.is_some() && self.sent >= self.posts.len()
+    }
+}
+
+impl RunState {
+    pub fn load(path: &Path) -> Result<RunState, BotError> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|err| BotError::Config(format!("invalid toplists {}: {err}", path.display())))
+    }
+
+    /// Write the state to `path`, replacing it atomically.
+    pub fn save(&self, path: &Path) -> Result<(), BotError> {
+        let contents =
+            serde_json::to_string_pretty(self).map_err(|err| BotError::Io(err.into()))?;
+        file_utils::write_atomically(path, &contents)
+    }
+
+    /// Remove the state file after a run completed.
+    pub fn remove(path: &Path) -> Result<(), BotError> {
+        match std::fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -18,11 +18,125 @@ pub fn parse_iso_week(week_param_opt: Option<&str>) -> Result<IsoWeek, Box<dyn s
     }
 }
 
+/// Parse a month relative to the current one (`+0`, `-1`) or in the format `yyyy-mm`.
+pub fn parse_month(month_param: &str) -> Result<Period, Box<dyn std::error::Error>> {
+    let first_char = month_param.chars().next().ok_or("month parameter empty")?;
+    let (year, month) = if first_char == '+' || first_char == '-' {
+        let month_offset: i32 = month_param.parse()?;
+        let today = Local::now();
+        let months = today.year() * 12 + today.month0() as i32 + month_offset;
+        (months.div_euclid(12), months.rem_euclid(12) as u32 + 1)
+    } else {
+        let (year_str, month_str) = month_param
+            .split_once("-")
+            .ok_or("bad month format (expected `yyyy-mm`)")?;
+        (year_str.parse()?, month_str.parse()?)
+    };
+    Ok(Period::month(year, month).ok_or("invalid month")?)
+}
+
+/// Parse a year relative to the current one (`+0`, `-1`) or in the format `yyyy`.
+pub fn parse_year(year_param: &str) -> Result<Period, Box<dyn std::error::Error>> {
+    let first_char = year_param.chars().next().ok_or("year parameter empty")?;
+    let year = if first_char == '+' || first_char == '-' {
+        let year_offset: i32 = year_param.parse()?;
+        Local::now().iso_week().year() + year_offset
+    } else {
+        year_param.parse()?
+    };
+    Ok(Period::year(year).ok_or("invalid year")?)
+}
+
 pub fn iso_week_to_datetime(naive: IsoWeek) -> DateTime<Utc> {
     let naive_date = NaiveDate::from_isoywd_opt(naive.year(), naive.week(), Weekday::Mon).unwrap();
     DateTime::from_naive_utc_and_offset(naive_date.and_hms_opt(0, 0, 0).unwrap(), Utc)
 }
 
+/// A time span to build toplists for, from `start` (inclusive) to `end` (exclusive).
+#[derive(Debug, Clone)]
+pub struct Period {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Human-readable name, used for thread names.
+    pub label: String,
+}
+
+impl Period {
+    pub fn week(week: IsoWeek) -> Self {
+        let start = iso_week_to_datetime(week);
+        Period {
+            start,
+            end: start + Duration::weeks(1),
+            label: format!("{:?}", week),
+        }
+    }
+
+    /// All weeks from `from` to `to`, both inclusive.
+    pub fn weeks(from: IsoWeek, to: IsoWeek) -> Result<Self, String> {
+        if from == to {
+            return Ok(Period::week(from));
+        }
+        if from > to {
+            return Err(format!("{:?} is after {:?}", from, to));
+        }
+        Ok(Period {
+            start: iso_week_to_datetime(from),
+            end: iso_week_to_datetime(to) + Duration::weeks(1),
+            label: format!("{:?} to {:?}", from, to),
+        })
+    }
+
+    /// The weeks starting in the given month,
+    /// so that every week belongs to exactly one month.
+    pub fn month(year: i32, month: u32) -> Option<Self> {
+        let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let next = first.checked_add_months(Months::new(1))?;
+        Some(Period {
+            start: first_monday_from(first),
+            end: first_monday_from(next),
+            label: first.format("%B %Y").to_string(),
+        })
+    }
+
+    /// The weeks of the given ISO week-numbering year.
+    pub fn year(year: i32) -> Option<Self> {
+        let start = NaiveDate::from_isoywd_opt(year, 1, Weekday::Mon)?;
+        let end = NaiveDate::from_isoywd_opt(year + 1, 1, Weekday::Mon)?;
+        Some(Period {
+            start: start.and_time(NaiveTime::MIN).and_utc(),
+            end: end.and_time(NaiveTime::MIN).and_utc(),
+            label: year.to_string(),
+        })
+    }
+
+    /// The ISO week of this period, if it is exactly one week.
+    pub fn as_week(&self) -> Option<IsoWeek> {
+        let week = self.start.iso_week();
+        (iso_week_to_datetime(week) == self.start && self.end - self.start == Duration::weeks(1))
+            .then_some(week)
+    }
+
+    /// The ISO weeks this period consists of, in order.
+    pub fn split_weeks(&self) -> Vec<Period> {
+        let mut weeks = Vec::new();
+        let mut start = self.start;
+        while start < self.end {
+            let week = Period::week(start.iso_week());
+            start = week.end;
+            weeks.push(week);
+        }
+        weeks
+    }
+}
+
+/// Midnight of the first Monday on or after `date`.
+fn first_monday_from(date: NaiveDate) -> DateTime<Utc> {
+    let days = (7 - date.weekday().num_days_from_monday()) % 7;
+    (date + Duration::days(days.into()))
+        .and_time(NaiveTime::MIN)
+        .and_utc()
+}
+
 /// Discord's epoch starts at "2015-01-01T00:00:00+00:00"
 const DISCORD_EPOCH: u64 = 1_420_070_400_000;
 
@@ -42,8 +156,77 @@ pub fn time_snowflake(datetime: DateTime<Utc>, high: bool) -> u64 {
     (discord_millis << 22) + (if high { (1 << 22) - 1 } else { 0 })
 }
 
+/// The earliest time a snowflake (id) can represent.
+pub fn discord_epoch() -> DateTime<Utc> {
+    snowflake_time(0u64)
+}
+
 /// Extract the timestamp of a snowflake (id).
 pub fn snowflake_time<T: Into<u64>>(id: T) -> DateTime<Utc> {
     Utc.timestamp_millis_opt(((id.into() >> 22) + DISCORD_EPOCH) as i64)
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> DateTime<Utc> {
+        date.parse::<NaiveDate>()
+            .unwrap()
+            .and_time(NaiveTime::MIN)
+            .and_utc()
+    }
+
+    fn labels(period: &Period) -> Vec<String> {
+        period
+            .split_weeks()
+            .into_iter()
+            .map(|week| week.label)
+            .collect()
+    }
+
+    #[test]
+    fn months_consist_of_the_weeks_starting_in_them() {
+        let march = Period::month(2025, 3).unwrap();
+        assert_eq!(march.label, "March 2025");
+        assert_eq!(
+            (march.start, march.end),
+            (date("2025-03-03"), date("2025-04-07"))
+        );
+        assert_eq!(
+            labels(&march),
+            ["2025-W10", "2025-W11", "2025-W12", "2025-W13", "2025-W14"]
+        );
+
+        // A month starting on a Monday begins with it.
+        let september = Period::month(2025, 9).unwrap();
+        assert_eq!(september.start, date("2025-09-01"));
+        assert_eq!(september.end, date("2025-10-06"));
+        assert!(Period::month(2025, 13).is_none());
+    }
+
+    #[test]
+    fn years_consist_of_their_iso_weeks() {
+        let year = Period::year(2025).unwrap();
+        assert_eq!(
+            (year.start, year.end),
+            (date("2024-12-30"), date("2025-12-29"))
+        );
+        let weeks = year.split_weeks();
+        assert_eq!(weeks.len(), 52);
+        assert_eq!(weeks[0].label, "2025-W01");
+        assert_eq!(weeks[51].label, "2025-W52");
+
+        assert_eq!(Period::year(2026).unwrap().split_weeks().len(), 53);
+    }
+
+    #[test]
+    fn split_weeks_keeps_single_weeks() {
+        let week = Period::week(parse_iso_week(Some("2025-10")).unwrap());
+        let weeks = week.split_weeks();
+        assert_eq!(weeks.len(), 1);
+        assert_eq!((weeks[0].start, weeks[0].end), (week.start, week.end));
+        assert_eq!(weeks[0].as_week(), week.as_week());
+    }
+}
@@ -1,52 +1,105 @@
 use std::cmp::Ordering;
 use std::collections::{BTreeSet, HashMap, HashSet};
-use std::sync::Arc;
 
-use serenity::futures::future::try_join_all;
-use serenity::http::Http;
-use serenity::model::channel::{Message, MessageReaction, ReactionType};
-use serenity::model::prelude::CurrentUser;
+use serde::{Deserialize, Serialize};
+use serenity::futures::stream::{FuturesOrdered, StreamExt};
+use serenity::model::channel::{
+    Attachment as MessageAttachment, Message, MessageReaction, MessageReferenceKind, ReactionType,
+};
+use serenity::model::id::{AttachmentId, ChannelId, GuildId, MessageId, UserId};
 use serenity::prelude::SerenityError;
 
-use crate::config::{Config, Emoji};
+use crate::config::{Config, Emoji, Matching};
+use crate::retry::Retrier;
+use crate::source::DiscordSource;
 
 #[derive(Debug)]
 pub struct Toplist<'c> {
     config: &'c Config,
-    current_user: CurrentUser,
-    http: Arc<Http>,
-    pub top: HashMap<Emoji, BTreeSet<MsgWrap>>,
-    pub other_prep: BTreeSet<MsgWrap>,
-    pub other: BTreeSet<MsgWrap>,
+    retrier: &'c Retrier,
+    source: &'c dyn DiscordSource,
+    /// The bot's own reactions are not counted.
+    current_user: Option<UserId>,
+    /// Whether the 'Other' toplist is built.
+    other_enabled: bool,
+    pub top: HashMap<Emoji, BTreeSet<Entry>>,
+    pub other_prep: BTreeSet<Entry>,
+    pub other: BTreeSet<Entry>,
+    /// Activity per toplist, `None` being 'Other'.
+    activity: HashMap<Option<Emoji>, Tally>,
 }
 
 impl<'c> Toplist<'c> {
-    pub fn new(config: &'c Config, current_user: &CurrentUser, http: Arc<Http>) -> Self {
+    pub fn new(
+        config: &'c Config,
+        retrier: &'c Retrier,
+        source: &'c dyn DiscordSource,
+        current_user: Option<UserId>,
+    ) -> Self {
         Toplist {
             config,
-            current_user: current_user.clone(),
-            http,
+            retrier,
+            source,
+            current_user,
+            other_enabled: config.other.enabled,
             top: Default::default(),
             other_prep: Default::default(),
             other: Default::default(),
+            activity: Default::default(),
         }
     }
 
+    /// Skip the 'Other' toplist, e.g. when it was not selected,
+    /// as it is the only one costing requests to build.
+    pub fn with_other(mut self, enabled: bool) -> Self {
+        self.other_enabled &= enabled;
+        self
+    }
+
+    /// Count the message into the toplists' activity
+    /// and add it to the toplists if it has content to post.
     pub async fn append(&mut self, message: &Message) {
-        let Some(content) = self.find_content(message).await else {
+        let content = self.find_content(message).await;
+        if content.is_none() {
             eprintln!("no content found for {}", message.id);
-            return;
-        };
+        }
 
-        self.append_known(message, &content);
-        if self.config.other.enabled {
-            self.append_other(message, &content);
+        self.append_known(message, content.as_ref());
+        if self.other_enabled
+            && self.config.source_channel(&self.config.other.channels) == message.channel_id
+        {
+            self.append_other(message, content.as_ref());
         }
     }
 
-    async fn find_content(&self, message: &Message) -> Option<String> {
-        if let Some(reference) = &message.message_reference {
+    /// The activity on the toplist of `emoji`, or 'Other' for `None`.
+    pub fn stats(&self, emoji: &Option<Emoji>) -> Stats {
+        self.activity
+            .get(emoji)
+            .map(|tally| Stats {
+                reactions: tally.reactions,
+                posts: tally.posts,
+                authors: tally.authors.len() as u64,
+                winner: 0,
+            })
+            .unwrap_or_default()
+    }
+
+    fn tally(&mut self, emoji: Option<Emoji>, message: &Message, reactions: u64) {
+        let tally = self.activity.entry(emoji).or_default();
+        tally.reactions += reactions;
+        tally.posts += 1;
+        tally.authors.insert(message.author.id);
+    }
+
+    async fn find_content(&self, message: &Message) -> Option<Content> {
+        let forwarded = message
+            .message_reference
+            .as_ref()
+            .filter(|reference| reference.kind == MessageReferenceKind::Forward);
+        if let Some(reference) = forwarded {
             // Try to recursively follow forwarded messages.
+            // Replies reference a message as well, but have content of their own.
             // Realistically, we won't have access to messages from other servers, however.
             let Some(mid) = reference.message_id else {
                 eprintln!(
@@ -55,7 +108,13 @@ impl<'c> Toplist<'c> {
                 );
                 return None;
             };
-            let forwarded_message = match self.http.get_message(reference.channel_id, mid).await {
+            let forwarded_message = match self
+                .retrier
+                .run("resolving forwarded message", || {
+                    self.source.message(reference.channel_id, mid)
+                })
+                .await
+            {
                 Ok(msg) => msg,
                 Err(err) => {
                     eprintln!(
@@ -71,36 +130,51 @@ impl<'c> Toplist<'c> {
             );
             Box::pin(self.find_content(&forwarded_message)).await
         } else {
-            let lines = std::iter::once(message.content.clone())
-                .chain(message.attachments.iter().map(|t| t.url.clone()));
-            Some(itertools::Itertools::intersperse(lines, "\n".to_owned()).collect::<String>())
-                .filter(|s| !s.is_empty())
+            Some(Content {
+                text: message.content.clone(),
+                attachments: message.attachments.iter().map(Attachment::from).collect(),
+            })
+            .filter(|c| !c.is_empty())
         }
     }
 
-    fn append_known(&mut self, message: &Message, content: &str) {
+    fn append_known(&mut self, message: &Message, content: Option<&Content>) {
         for entry in self.config.toplist.iter() {
-            let Some(count) = message
+            if self.config.source_channel(&entry.channels) != message.channel_id {
+                continue;
+            }
+            // With merged skin tones, several reactions can match the same emoji.
+            // Their sum is an approximation, like for the 'Other' toplist.
+            let matching: Vec<_> = message
                 .reactions
                 .iter()
-                .find_map(|r| is_same_emoji(r, &entry.emoji).then_some(r.count - r.me as u64))
-            else {
+                .filter(|r| is_same_emoji(r, &entry.emoji, &self.config.matching))
+                .collect();
+            if matching.is_empty() {
+                continue;
+            }
+            let count: u64 = matching.iter().map(|r| r.count - r.me as u64).sum();
+            if count == 0 {
+                continue;
+            }
+            self.tally(Some(entry.emoji.clone()), message, count);
+            let Some(content) = content else {
                 continue;
             };
 
             let list = self.top.entry(entry.emoji.clone()).or_default();
             if Self::prepare_list_for_insert(list, entry.max, count).is_some() {
-                let msg_wrap = MsgWrap {
+                list.insert(Entry::new(
+                    message,
                     count,
-                    message: message.clone(),
-                    content: content.to_string(),
-                };
-                list.insert(msg_wrap);
+                    content.clone(),
+                    message.reactions.iter(),
+                ));
             }
         }
     }
 
-    fn append_other(&mut self, message: &Message, content: &str) {
+    fn append_other(&mut self, message: &Message, content: Option<&Content>) {
         let stripped_reactions: Vec<_> = message
             .reactions
             .iter()
@@ -110,9 +184,8 @@ impl<'c> Toplist<'c> {
                     .other
                     .ignore
                     .iter()
-                    .any(|ignore| is_same_emoji(r, ignore))
+                    .any(|ignore| is_same_emoji(r, ignore, &self.config.matching))
             })
-            .cloned()
             .collect();
 
         // This is an approximation of the actual count
@@ -126,22 +199,20 @@ impl<'c> Toplist<'c> {
         if count == 0 {
             return;
         }
+        self.tally(None, message, count);
+        let Some(content) = content else {
+            return;
+        };
 
-        let mut message = message.clone();
-        message.reactions = stripped_reactions;
-        let msg_wrap = MsgWrap {
-            count,
+        self.other_prep.insert(Entry::new(
             message,
-            content: content.to_string(),
-        };
-        self.other_prep.insert(msg_wrap);
+            count,
+            content.clone(),
+            stripped_reactions.into_iter(),
+        ));
     }
 
-    fn prepare_list_for_insert(
-        list: &mut BTreeSet<MsgWrap>,
-        max: usize,
-        count: u64,
-    ) -> Option<u64> {
+    fn prepare_list_for_insert(list: &mut BTreeSet<Entry>, max: usize, count: u64) -> Option<u64> {
         if count == 0 {
             return None;
         }
@@ -154,14 +225,14 @@ impl<'c> Toplist<'c> {
     }
 
     pub async fn finalize(&mut self) -> Result<(), SerenityError> {
-        if !self.config.other.enabled {
+        if !self.other_enabled {
             return Ok(());
         }
 
         let ids_to_ignore: HashSet<_> = self
             .top
             .values()
-            .flat_map(|list| list.iter().map(|wrap| wrap.message.id))
+            .flat_map(|list| list.iter().map(|item| item.id))
             .collect();
 
         eprintln!(
@@ -170,31 +241,60 @@ impl<'c> Toplist<'c> {
             ids_to_ignore.len(),
         );
 
+        // Candidates are evaluated in order of their approximate count,
+        // a few at a time, so that the early exit still applies
+        // while not waiting for each message's users one after another.
+        let this = &*self;
+        let concurrency = this.config.scan.concurrency.max(1);
+        let mut candidates = this
+            .other_prep
+            .iter()
+            .rev()
+            .enumerate()
+            .filter(|(_, item)| !ids_to_ignore.contains(&item.id));
+        let mut in_flight = FuturesOrdered::new();
+        let mut other = BTreeSet::new();
         let mut min = 0;
-        for (i, wrap) in self.other_prep.iter().rev().enumerate() {
-            if wrap.count <= min {
-                // Impossible to have more unique users than sum of reactions
-                eprintln!("Early-exiting 'Other' collection after {i} posts");
-                break;
+        let mut exhausted = false;
+        loop {
+            while !exhausted && in_flight.len() < concurrency {
+                match candidates.next() {
+                    Some((i, item)) if item.count > min => {
+                        in_flight.push_back(async move {
+                            (i, item, this.count_distinct_users(item).await)
+                        });
+                    }
+                    Some((i, _)) => {
+                        // Impossible to have more unique users than sum of reactions
+                        eprintln!("Early-exiting 'Other' collection after {i} posts");
+                        exhausted = true;
+                    }
+                    None => exhausted = true,
+                }
             }
-            if ids_to_ignore.contains(&wrap.message.id) {
+            let Some((i, item, count)) = in_flight.next().await else {
+                break;
+            };
+            let count = count?;
+            if item.count <= min {
+                // The minimum was raised while counting this one.
                 continue;
             }
 
-            let count = self.count_distinct_users(&wrap.message).await?;
             let Some(new_min) =
-                Self::prepare_list_for_insert(&mut self.other, self.config.other.max, count)
+                Self::prepare_list_for_insert(&mut other, this.config.other.max, count)
             else {
                 continue;
             };
             eprintln!("Adding post {i} to 'Other' collection (with {count}), new min: {new_min}");
-            let new_wrap = MsgWrap {
+            other.insert(Entry {
                 count,
-                ..wrap.clone()
-            };
-            self.other.insert(new_wrap);
+                ..item.clone()
+            });
             min = new_min;
         }
+        drop(in_flight);
+        self.other = other;
         eprintln!(
             "Collected {} messages for the 'Other' toplist",
             self.other.len()
@@ -202,7 +302,7 @@ impl<'c> Toplist<'c> {
         Ok(())
     }
 
-    fn min_count(list: &BTreeSet<MsgWrap>, max_entries: usize) -> u64 {
+    fn min_count(list: &BTreeSet<Entry>, max_entries: usize) -> u64 {
         if list.len() < max_entries {
             0
         } else {
@@ -210,64 +310,371 @@ impl<'c> Toplist<'c> {
         }
     }
 
-    async fn count_distinct_users(&self, message: &Message) -> Result<u64, SerenityError> {
-        let futures: Vec<_> = message
-            .reactions
-            .iter()
-            .map(|r| {
-                message.reaction_users(
-                    &self.http,
-                    r.reaction_type.clone(),
-                    Some(self.config.per_reaction_limit),
-                    None,
-                )
-            })
-            .collect();
-
-        let mut users: HashSet<_> = try_join_all(futures)
-            .await?
-            .iter()
-            .flat_map(|users| users.into_iter().map(|user| user.id))
-            .collect();
+    /// The reactions are requested one after another
+    /// to keep the number of concurrent requests at one per message.
+    async fn count_distinct_users(&self, item: &Entry) -> Result<u64, SerenityError> {
+        let mut users = HashSet::new();
+        for r in &item.reactions {
+            let reaction_users = self
+                .retrier
+                .run("fetching reaction users", || {
+                    self.source.reaction_users(
+                        item.channel_id,
+                        item.id,
+                        r.reaction.clone(),
+                        self.config.per_reaction_limit,
+                    )
+                })
+                .await?;
+            users.extend(reaction_users);
+        }
 
-        users.remove(&self.current_user.id);
+        if let Some(current_user) = &self.current_user {
+            users.remove(current_user);
+        }
         Ok(users.len() as u64)
     }
 }
 
-fn is_same_emoji(r: &MessageReaction, emoji: &Emoji) -> bool {
+fn is_same_emoji(r: &MessageReaction, emoji: &Emoji, matching: &Matching) -> bool {
     match (&r.reaction_type, emoji) {
         (ReactionType::Custom { id, .. }, Emoji::Custom { id: id2, .. }) if id == id2 => true,
-        (ReactionType::Unicode(s), Emoji::Unicode { string, .. }) if s == string => true,
+        (ReactionType::Unicode(s), Emoji::Unicode { string, .. }) => {
+            matching.normalize_unicode(s) == matching.normalize_unicode(string)
+        }
         _ => false,
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct MsgWrap {
+/// Activity on the emoji of a toplist during its period,
+/// to compare it with the previous week.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// The number of reactions, without the bot's own.
+    /// For 'Other', the sum of all reactions that are not ignored.
+    pub reactions: u64,
+    /// The number of messages with such reactions.
+    pub posts: u64,
+    /// The number of distinct authors of these messages.
+    pub authors: u64,
+    /// The count of the winning entry.
+    pub winner: u64,
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    reactions: u64,
+    posts: u64,
+    authors: HashSet<UserId>,
+}
+
+/// The postable content of a message, after following forwards.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Content {
+    pub text: String,
+    pub attachments: Vec<Attachment>,
+}
+
+impl Content {
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.attachments.is_empty()
+    }
+
+    /// Text and attachment URLs, one per line.
+    pub fn joined(&self) -> String {
+        let lines = std::iter::once(self.text.clone())
+            .filter(|text| !text.is_empty())
+            .chain(self.attachments.iter().map(|t| t.url.clone()));
+        itertools::Itertools::intersperse(lines, "\n".to_owned()).collect()
+    }
+
+    pub fn first_image(&self) -> Option<&Attachment> {
+        self.attachments.iter().find(|t| is_image(t))
+    }
+
+    /// Attachments that cannot be displayed as an embed image.
+    pub fn other_attachments(&self) -> impl Iterator<Item = &Attachment> {
+        let first_image = self.first_image().map(|t| t.id);
+        self.attachments
+            .iter()
+            .filter(move |t| Some(t.id) != first_image)
+    }
+}
+
+fn is_image(attachment: &Attachment) -> bool {
+    attachment
+        .content_type
+        .as_deref()
+        .is_some_and(|t| t.starts_with("image/"))
+}
+
+/// The parts of an attachment needed to post it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attachment {
+    pub id: AttachmentId,
+    pub url: String,
+    pub content_type: Option<String>,
+}
+
+impl From<&MessageAttachment> for Attachment {
+    fn from(attachment: &MessageAttachment) -> Self {
+        Attachment {
+            id: attachment.id,
+            url: attachment.url.clone(),
+            content_type: attachment.content_type.clone(),
+        }
+    }
+}
+
+/// A message ranked in a toplist,
+/// keeping only what is needed to rank and render it
+/// instead of the whole message.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
     pub count: u64,
-    pub content: String,
-    pub message: Message,
+    pub id: MessageId,
+    pub channel_id: ChannelId,
+    pub guild_id: Option<GuildId>,
+    pub author: Author,
+    /// The counted reactions, without the bot's own.
+    pub reactions: Vec<ReactionCount>,
+    pub content: Content,
 }
 
-impl PartialEq for MsgWrap {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Author {
+    pub id: UserId,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReactionCount {
+    pub reaction: ReactionType,
+    pub count: u64,
+}
+
+impl Entry {
+    fn new<'m>(
+        message: &Message,
+        count: u64,
+        content: Content,
+        reactions: impl Iterator<Item = &'m MessageReaction>,
+    ) -> Self {
+        Entry {
+            count,
+            id: message.id,
+            channel_id: message.channel_id,
+            guild_id: message.guild_id,
+            author: Author {
+                id: message.author.id,
+                name: message.author.name.clone(),
+            },
+            reactions: reactions
+                .map(|r| ReactionCount {
+                    reaction: r.reaction_type.clone(),
+                    count: r.count - r.me as u64,
+                })
+                .collect(),
+            content,
+        }
+    }
+
+    pub fn link(&self) -> String {
+        self.id.link(self.channel_id, self.guild_id)
+    }
+}
+
+impl PartialEq for Entry {
     fn eq(&self, other: &Self) -> bool {
-        self.message.id == other.message.id
+        self.id == other.id
     }
 }
 
-impl Eq for MsgWrap {}
+impl Eq for Entry {}
 
-impl PartialOrd for MsgWrap {
+impl PartialOrd for Entry {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl Ord for MsgWrap {
+impl Ord for Entry {
     fn cmp(&self, other: &Self) -> Ordering {
         self.count
             .cmp(&other.count)
-            .then_with(|| self.message.id.cmp(&other.message.id))
+            .then_with(|| self.id.cmp(&other.id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::model::channel::MessageReference;
+
+    use super::*;
+    use crate::source::testing::{TestSource, BOT_USER_ID, CHANNEL_ID};
+
+    fn config(other_max: usize) -> Config {
+        toml::from_str(&format!(
+            r#"
+            channel_id = {CHANNEL_ID}
+            toplist = [{{ emoji.string = "⭐" }}]
+            other = {{ enabled = true, max = {other_max}, ignore = [{{ string = "⭐" }}] }}
+            scan = {{ concurrency = 1 }}
+            "#
+        ))
+        .unwrap()
+    }
+
+    fn texts(list: &BTreeSet<Entry>) -> Vec<(&str, u64)> {
+        list.iter()
+            .rev()
+            .map(|entry| (entry.content.text.as_str(), entry.count))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn finalize_exits_early() {
+        let config = config(1);
+        let retrier = Retrier::new(&config.retry);
+        let source = TestSource::new("ranking.json");
+        let mut toplist = Toplist::new(&config, &retrier, &source, Some(BOT_USER_ID));
+        let messages = source.all_messages().await;
+        for message in &messages {
+            toplist.append(message).await;
+        }
+        toplist.finalize().await.unwrap();
+
+        assert_eq!(texts(&toplist.other), [("Four reactions by four users", 4)]);
+        // After that one, no message can have more users than the three it replaced.
+        let counted: BTreeSet<_> = source.requested.lock().unwrap().iter().copied().collect();
+        let content = |id| &messages.iter().find(|m| m.id == id).unwrap().content;
+        let counted: Vec<_> = counted.into_iter().map(content).collect();
+        assert_eq!(
+            counted,
+            [
+                "Five reactions by three users",
+                "Four reactions by four users"
+            ]
+        );
+    }
+
+    /// 'Other' with `other_max` entries, counted `concurrency` at a time,
+    /// and the number of messages whose users were requested.
+    async fn other(other_max: usize, concurrency: usize) -> (Vec<(String, u64)>, usize) {
+        let mut config = config(other_max);
+        config.scan.concurrency = concurrency;
+        let retrier = Retrier::new(&config.retry);
+        let source = TestSource::new("ranking.json");
+        let mut toplist = Toplist::new(&config, &retrier, &source, Some(BOT_USER_ID));
+        for message in &source.all_messages().await {
+            toplist.append(message).await;
+        }
+        toplist.finalize().await.unwrap();
+        let other = texts(&toplist.other)
+            .into_iter()
+            .map(|(text, count)| (text.to_owned(), count))
+            .collect();
+        let requested: HashSet<_> = source.requested.lock().unwrap().iter().copied().collect();
+        (other, requested.len())
+    }
+
+    #[tokio::test]
+    async fn concurrent_finalize_finds_the_same() {
+        for other_max in 1..=3 {
+            let (expected, sequential) = other(other_max, 1).await;
+            for concurrency in 2..=4 {
+                let (other, requested) = other(other_max, concurrency).await;
+                assert_eq!(other, expected, "concurrency {concurrency}");
+                // At most the candidates already in flight when the early exit applies.
+                assert!(
+                    requested < sequential + concurrency,
+                    "{requested} requests with concurrency {concurrency}"
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn only_forwards_are_followed() {
+        let config = config(15);
+        let retrier = Retrier::new(&config.retry);
+        let source = TestSource::new("ranking.json");
+        let toplist = Toplist::new(&config, &retrier, &source, Some(BOT_USER_ID));
+        let original = source.all_messages().await.remove(0);
+
+        let mut message = original.clone();
+        message.id = MessageId::new(original.id.get() + 1);
+        message.content = "A reply".to_owned();
+        let mut reference = MessageReference::new(MessageReferenceKind::Default, CHANNEL_ID);
+        reference.message_id = Some(original.id);
+        message.message_reference = Some(reference.clone());
+        let content = toplist.find_content(&message).await.unwrap();
+        assert_eq!(content.text, "A reply");
+
+        message.content.clear();
+        reference.kind = MessageReferenceKind::Forward;
+        message.message_reference = Some(reference);
+        let content = toplist.find_content(&message).await.unwrap();
+        assert_eq!(content.text, original.content);
+    }
+
+    #[tokio::test]
+    async fn own_reactions_are_not_counted() {
+        let config = config(15);
+        let retrier = Retrier::new(&config.retry);
+        let source = TestSource::new("ranking.json");
+        let mut toplist = Toplist::new(&config, &retrier, &source, Some(BOT_USER_ID));
+        for message in &source.all_messages().await {
+            toplist.append(message).await;
+        }
+        toplist.finalize().await.unwrap();
+
+        assert_eq!(
+            texts(&toplist.top[&config.toplist[0].emoji]),
+            [
+                ("Second of two with four stars", 4),
+                ("First of two with four stars", 4),
+                ("Two stars and the bot's", 2),
+                ("Two stars", 2),
+            ]
+        );
+        assert_eq!(
+            texts(&toplist.other),
+            [
+                ("Four reactions by four users", 4),
+                ("Three reactions by three users", 3),
+                ("Five reactions by three users", 3),
+                ("Three reactions including the bot's", 2),
+            ]
+        );
+        let stats = toplist.stats(&None);
+        assert_eq!((stats.reactions, stats.posts, stats.authors), (14, 4, 2));
+
+        let entry = toplist
+            .other
+            .iter()
+            .find(|entry| entry.content.text == "Three reactions including the bot's")
+            .unwrap();
+        assert_eq!(toplist.count_distinct_users(entry).await.unwrap(), 2);
+        toplist.current_user = None;
+        assert_eq!(toplist.count_distinct_users(entry).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn other_can_be_skipped() {
+        let config = config(15);
+        let retrier = Retrier::new(&config.retry);
+        let source = TestSource::new("ranking.json");
+        let mut toplist =
+            Toplist::new(&config, &retrier, &source, Some(BOT_USER_ID)).with_other(false);
+        for message in &source.all_messages().await {
+            toplist.append(message).await;
+        }
+        toplist.finalize().await.unwrap();
+
+        assert_eq!(toplist.top[&config.toplist[0].emoji].len(), 4);
+        assert!(toplist.other_prep.is_empty());
+        assert!(toplist.other.is_empty());
+        assert!(source.requested.lock().unwrap().is_empty());
     }
 }
@@ -0,0 +1,378 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fmt;
+
+use serenity::http::Http;
+use serenity::model::guild::Emoji as GuildEmoji;
+use serenity::model::id::{ChannelId, GuildId};
+
+use crate::config::{Channels, Config, Emoji};
+use crate::error::BotError;
+use crate::preflight;
+use crate::retry::Retrier;
+
+/// Maximum number of users Discord returns for a single reaction request.
+const MAX_PER_REACTION_LIMIT: u8 = 100;
+
+/// Toplists longer than this make for very long threads.
+const LARGE_MAX: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+/// A problem with the configuration, with a hint on how to fix it.
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    pub hint: Option<String>,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>) -> Self {
+        Diagnostic {
+            level: Level::Error,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn warning(message: impl Into<String>) -> Self {
+        Diagnostic {
+            level: Level::Warning,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        write!(f, "{level}: {}", self.message)?;
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  hint: {hint}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Check the configuration without contacting Discord.
+pub fn check_offline(config: &Config) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    if !(1..=MAX_PER_REACTION_LIMIT).contains(&config.per_reaction_limit) {
+        diagnostics.push(
+            Diagnostic::error(format!(
+                "`per_reaction_limit` is {}",
+                config.per_reaction_limit
+            ))
+            .hint(format!(
+                "use a value between 1 and {MAX_PER_REACTION_LIMIT}"
+            )),
+        );
+    }
+
+    if config.toplist.is_empty() && !config.other.enabled {
+        diagnostics.push(
+            Diagnostic::warning("no toplists configured")
+                .hint("add a `[[toplist]]` entry or set `other.enabled = true`"),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    for (i, entry) in config.toplist.iter().enumerate() {
+        let what = format!("toplist #{} ({})", i + 1, describe(&entry.emoji));
+        if !seen.insert(config.matching.normalize(&entry.emoji)) {
+            diagnostics.push(
+                Diagnostic::error(format!("{what} is configured more than once"))
+                    .hint("remove the duplicate `[[toplist]]` entry"),
+            );
+        }
+        check_max(&mut diagnostics, &what, entry.max);
+        check_emoji(&mut diagnostics, &what, &entry.emoji);
+        let normalized = config.matching.normalize(&entry.emoji);
+        if config
+            .other
+            .ignore
+            .iter()
+            .any(|ignore| config.matching.normalize(ignore) == normalized)
+        {
+            diagnostics.push(Diagnostic::warning(format!(
+                "{what} is also ignored for the 'Other' toplist"
+            )));
+        }
+    }
+
+    if config.other.enabled {
+        check_max(&mut diagnostics, "the 'Other' toplist", config.other.max);
+    }
+    let mut seen = HashSet::new();
+    for emoji in &config.other.ignore {
+        let what = format!("ignored emoji {}", describe(emoji));
+        if !seen.insert(config.matching.normalize(emoji)) {
+            diagnostics.push(Diagnostic::warning(format!(
+                "{what} is listed more than once"
+            )));
+        }
+        check_emoji(&mut diagnostics, &what, emoji);
+    }
+
+    if config.retry.max_attempts == 0 {
+        diagnostics.push(
+            Diagnostic::warning("`retry.max_attempts` is 0 and will be treated as 1")
+                .hint("set it to 1 to disable retrying"),
+        );
+    }
+
+    if config.best_of.eligible_rank == 0 {
+        diagnostics.push(
+            Diagnostic::error("`best_of.eligible_rank` is 0 and best-of toplists would be empty")
+                .hint("set it to 1 to only include the winners of each week"),
+        );
+    }
+
+    if config.scan.concurrency == 0 {
+        diagnostics.push(
+            Diagnostic::warning("`scan.concurrency` is 0 and will be treated as 1")
+                .hint("set it to 1 to fetch one slice at a time"),
+        );
+    }
+    if config.scan.slice_hours == 0 {
+        diagnostics.push(Diagnostic::warning(
+            "`scan.slice_hours` is 0 and will be treated as 1",
+        ));
+    }
+
+    diagnostics
+}
+
+fn check_max(diagnostics: &mut Vec<Diagnostic>, what: &str, max: usize) {
+    if max == 0 {
+        diagnostics.push(
+            Diagnostic::error(format!("{what} has `max = 0` and would always be empty"))
+                .hint("remove the toplist or increase `max`"),
+        );
+    } else if max > LARGE_MAX {
+        diagnostics.push(Diagnostic::warning(format!(
+            "{what} has `max = {max}`, which results in a very long thread"
+        )));
+    }
+}
+
+fn check_emoji(diagnostics: &mut Vec<Diagnostic>, what: &str, emoji: &Emoji) {
+    match emoji {
+        Emoji::Unicode { string } if string.is_empty() => {
+            diagnostics.push(Diagnostic::error(format!(
+                "{what} has an empty emoji string"
+            )));
+        }
+        Emoji::Unicode { string } if emojis::get(string).is_none() => {
+            diagnostics.push(
+                Diagnostic::warning(format!("{what} is not a known emoji"))
+                    .hint("make sure to copy the emoji exactly as Discord shows it"),
+            );
+        }
+        Emoji::Named { name } if name.is_empty() => {
+            diagnostics.push(Diagnostic::error(format!("{what} has an empty name")));
+        }
+        Emoji::Custom { name, .. } if name.is_empty() => {
+            diagnostics.push(
+                Diagnostic::warning(format!("{what} has an empty name"))
+                    .hint("the name is used for thread titles"),
+            );
+        }
+        _ => {}
+    }
+}
+
+/// Check the configuration against Discord:
+/// whether the channels exist, the custom emojis belong to the guild
+/// and the permissions are sufficient.
+pub async fn check_online(config: &Config, retrier: &Retrier, http: &Http) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    let mut channels = vec![
+        ("channel_id".to_owned(), config.channel_id),
+        ("target_channel_id".to_owned(), config.target_channel_id()),
+    ];
+    for (i, entry) in config.toplist.iter().enumerate() {
+        channels.extend(overrides(&format!("toplist #{}", i + 1), &entry.channels));
+    }
+    channels.extend(overrides("other", &config.other.channels));
+
+    let mut guild_id: Option<GuildId> = None;
+    // Channels already reported, whose permissions cannot be checked.
+    let mut failed = BTreeSet::new();
+    for (key, channel_id) in channels {
+        let channel = match retrier
+            .run("fetching channel", || channel_id.to_channel(http))
+            .await
+        {
+            Ok(channel) => channel,
+            Err(err) => {
+                diagnostics.push(
+                    Diagnostic::error(format!("`{key}` {channel_id} cannot be fetched: {err}"))
+                        .hint("check the ID and that the bot was invited to the server"),
+                );
+                failed.insert(channel_id);
+                continue;
+            }
+        };
+        let Some(channel) = channel.guild() else {
+            diagnostics.push(Diagnostic::error(format!(
+                "`{key}` {channel_id} is not a server channel"
+            )));
+            failed.insert(channel_id);
+            continue;
+        };
+        match guild_id {
+            Some(id) if id != channel.guild_id => diagnostics.push(Diagnostic::warning(format!(
+                "`{key}` {channel_id} belongs to a different server than `channel_id`"
+            ))),
+            _ => guild_id = Some(channel.guild_id),
+        }
+    }
+
+    let Some(guild_id) = guild_id else {
+        return diagnostics;
+    };
+
+    match retrier
+        .run("fetching guild emojis", || guild_id.emojis(http))
+        .await
+    {
+        Ok(guild_emojis) => diagnostics.extend(check_emojis(config, &guild_emojis)),
+        Err(err) => diagnostics.push(Diagnostic::error(format!(
+            "the custom emoji of the server cannot be fetched: {err}"
+        ))),
+    }
+    diagnostics.extend(check_permissions(config, retrier, http, &failed).await);
+    diagnostics
+}
+
+/// Check the configured custom emoji against those of the server.
+fn check_emojis(config: &Config, guild_emojis: &[GuildEmoji]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let configured = config
+        .toplist
+        .iter()
+        .map(|entry| &entry.emoji)
+        .chain(&config.other.ignore);
+    for emoji in configured {
+        let Emoji::Custom { name, id } = emoji else {
+            continue;
+        };
+        let by_id = guild_emojis.iter().find(|e| e.id == *id);
+        let by_name = guild_emojis.iter().find(|e| e.name == *name);
+        match (by_id, by_name) {
+            (Some(found), _) if found.name != *name => diagnostics.push(
+                Diagnostic::warning(format!(
+                    "custom emoji {} is called \"{}\" in the server",
+                    describe(emoji),
+                    found.name
+                ))
+                .hint(format!("set `name = \"{}\"`", found.name)),
+            ),
+            (Some(_), _) => {}
+            (None, Some(found)) => diagnostics.push(
+                Diagnostic::error(format!(
+                    "custom emoji {} is not from this server, but one with the same name is",
+                    describe(emoji),
+                ))
+                .hint(format!("did you mean `id = {}`?", found.id)),
+            ),
+            (None, None) => diagnostics.push(
+                Diagnostic::warning(format!(
+                    "custom emoji {} is not from this server",
+                    describe(emoji)
+                ))
+                .hint("reactions with it are still counted, but the bot cannot display it"),
+            ),
+        }
+    }
+
+    diagnostics
+}
+
+/// Check the permissions in every channel except the `failed` ones, already reported.
+async fn check_permissions(
+    config: &Config,
+    retrier: &Retrier,
+    http: &Http,
+    failed: &BTreeSet<ChannelId>,
+) -> Option<Diagnostic> {
+    let user = match retrier
+        .run("fetching current user", || http.get_current_user())
+        .await
+    {
+        Ok(user) => user,
+        Err(err) => {
+            return Some(Diagnostic::error(format!(
+                "the bot's own user cannot be fetched: {err}"
+            )))
+        }
+    };
+    match preflight::missing_permissions(
+        config,
+        retrier,
+        http,
+        user.id,
+        preflight::Needs::ALL,
+        failed,
+    )
+    .await
+    {
+        Ok(problems) if problems.is_empty() => None,
+        Ok(problems) => Some(
+            Diagnostic::error(format!("missing permissions: {}", problems.join("; ")))
+                .hint("grant them to the bot's role or in the channel settings"),
+        ),
+        Err(err) => Some(Diagnostic::error(format!(
+            "the permissions cannot be checked: {err}"
+        ))),
+    }
+}
+
+/// The channels a toplist overrides, with the setting they come from.
+fn overrides(what: &str, channels: &Channels) -> Vec<(String, ChannelId)> {
+    [
+        ("channel_id", channels.channel_id),
+        ("target_channel_id", channels.target_channel_id),
+    ]
+    .into_iter()
+    .filter_map(|(key, channel_id)| Some((format!("{what} {key}"), channel_id?)))
+    .collect()
+}
+
+pub fn unresolved_emoji(name: &str) -> Diagnostic {
+    Diagnostic::error(format!(
+        "custom emoji :{name}: does not exist in the server"
+    ))
+    .hint("check the spelling or configure it with `name` and `id`")
+}
+
+pub fn unresolvable_emojis(err: &BotError) -> Diagnostic {
+    Diagnostic::error(format!(
+        "custom emoji configured by name cannot be looked up: {err}"
+    ))
+    .hint("they are looked up in the server of `channel_id`")
+}
+
+fn describe(emoji: &Emoji) -> String {
+    match emoji {
+        Emoji::Custom { name, id } => format!(":{name}: ({id})"),
+        Emoji::Unicode { string } => format!("{string:?}"),
+        Emoji::Named { name } => format!(":{name}:"),
+    }
+}
@@ -0,0 +1,235 @@
+//! Runs the bot against the mock Discord server (`--features mock-server`)
+//! and checks what it posted, as recorded by the server.
+#![cfg(feature = "mock-server")]
+
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+const CHANNEL_ID: &str = "1000000000000000001";
+
+/// A modifying request received by the mock server.
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    body: Value,
+}
+
+impl Request {
+    fn is(&self, method: &str, path_suffix: &str) -> bool {
+        self.method == method && self.path.ends_with(path_suffix)
+    }
+}
+
+/// The mock server serving `fixtures/week-2025-10.json`, stopped when dropped.
+struct MockDiscord {
+    server: Child,
+    port: u16,
+    record: PathBuf,
+    /// Number of requests returned by `new_requests` so far.
+    seen: usize,
+}
+
+impl MockDiscord {
+    fn start(dir: &Path) -> Self {
+        let port = TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .unwrap()
+            .port();
+        let record = dir.join("record.jsonl");
+        let server = Command::new(env!("CARGO_BIN_EXE_mock_discord"))
+            .arg("--port")
+            .arg(port.to_string())
+            .arg("--fixture")
+            .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/week-2025-10.json"))
+            .arg("--record")
+            .arg(&record)
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let started = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "the mock server did not start"
+            );
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        MockDiscord {
+            server,
+            port,
+            record,
+            seen: 0,
+        }
+    }
+
+    /// The requests recorded since the last call.
+    fn new_requests(&mut self) -> Vec<Request> {
+        let contents = std::fs::read_to_string(&self.record).unwrap_or_default();
+        let requests: Vec<_> = contents
+            .lines()
+            .skip(self.seen)
+            .map(|line| {
+                let mut line: Value = serde_json::from_str(line).unwrap();
+                Request {
+                    method: line["method"].as_str().unwrap().to_owned(),
+                    path: line["path"].as_str().unwrap().to_owned(),
+                    body: line["body"].take(),
+                }
+            })
+            .collect();
+        self.seen += requests.len();
+        requests
+    }
+}
+
+impl Drop for MockDiscord {
+    fn drop(&mut self) {
+        self.server.kill().ok();
+        self.server.wait().ok();
+    }
+}
+
+/// Run the bot with the configuration in `dir`, returning its exit code.
+fn try_run_bot(dir: &Path, args: &[&str]) -> Option<i32> {
+    let output = Command::new(env!("CARGO_BIN_EXE_reaction_toplist_bot"))
+        .arg("--config")
+        .arg(dir.join("config.toml"))
+        .args(args)
+        .env("DISCORD_TOKEN", "mock-token")
+        .env_remove("DISCORD_TOKEN_FILE")
+        .output()
+        .unwrap();
+    if !output.status.success() {
+        eprintln!(
+            "{args:?} failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    output.status.code()
+}
+
+/// Run the bot with the configuration in `dir`, expecting it to succeed.
+fn run_bot(dir: &Path, args: &[&str]) {
+    assert_eq!(try_run_bot(dir, args), Some(0), "{args:?} failed");
+}
+
+fn write_config(dir: &Path, port: u16) {
+    let config = format!(
+        r#"
+        channel_id = {CHANNEL_ID}
+        api_url = "http://127.0.0.1:{port}"
+        state_file = "{state}"
+        history_file = "{history}"
+        toplist = [{{ emoji.string = "⭐", max = 3 }}, {{ emoji.string = "🙃", layout = "compact" }}]
+        other = {{ enabled = true, ignore = [{{ string = "♻" }}] }}
+        summary = {{ enabled = true, pin = true }}
+        retry = {{ max_attempts = 1 }}
+        "#,
+        state = dir.join("state.json").display(),
+        history = dir.join("history.json").display(),
+    );
+    std::fs::write(dir.join("config.toml"), config).unwrap();
+}
+
+fn thread_names(requests: &[Request]) -> Vec<&str> {
+    requests
+        .iter()
+        .filter(|request| request.is("POST", "/threads"))
+        .map(|request| request.body["name"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn posts_and_reruns() {
+    let dir = std::env::temp_dir().join(format!("toplist-mock-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let mut discord = MockDiscord::start(&dir);
+    write_config(&dir, discord.port);
+
+    run_bot(&dir, &["run", "2025-10"]);
+    let requests = discord.new_requests();
+    assert_eq!(
+        thread_names(&requests),
+        ["2025-W10 - ⭐", "2025-W10 - 🙃", "2025-W10 - Other"]
+    );
+    let contents: Vec<_> = requests
+        .iter()
+        .filter(|request| request.is("POST", "/messages"))
+        .filter_map(|request| request.body["content"].as_str())
+        .collect();
+    assert_eq!(
+        contents,
+        [
+            "```c\n3 // 2 users\n```",
+            "Friday night",
+            "by <@2000000000000000002> (bob)",
+            "```c\n2 // 3 users\n```",
+            "Monday morning",
+            "by <@2000000000000000001> (alice)",
+            "```c\n1 // 5 users\n```",
+            "Tuesday noon",
+            "by <@2000000000000000002> (bob)",
+        ]
+    );
+    let summary = requests
+        .iter()
+        .find(|request| request.is("POST", &format!("/channels/{CHANNEL_ID}/messages")))
+        .unwrap();
+    let description = summary.body["embeds"][0]["description"].as_str().unwrap();
+    assert!(description.starts_with("**⭐** · 5 users · "));
+    assert!(requests.iter().any(|request| request.method == "PUT"
+        && request
+            .path
+            .contains(&format!("/channels/{CHANNEL_ID}/pins/"))));
+    assert!(!dir.join("state.json").exists());
+
+    // The threads and the summary exist already, so nothing is posted again.
+    run_bot(&dir, &["run", "2025-10"]);
+    let requests = discord.new_requests();
+    assert!(
+        requests.iter().all(|request| request.is("POST", "/typing")),
+        "{requests:?}"
+    );
+
+    // Nothing to resume after a completed run.
+    run_bot(&dir, &["resume"]);
+    assert!(discord.new_requests().is_empty());
+
+    // Editing the messages in place, without creating anything.
+    run_bot(&dir, &["run", "2025-10", "--on-existing", "edit"]);
+    let requests = discord.new_requests();
+    assert!(thread_names(&requests).is_empty());
+    assert!(!requests
+        .iter()
+        .any(|request| request.is("POST", "/messages")));
+    let edited = requests
+        .iter()
+        .filter(|request| request.method == "PATCH" && request.path.contains("/messages/"))
+        .count();
+    // The nine classic posts, the compact one and the summary.
+    assert_eq!(edited, 11, "{requests:?}");
+
+    // Resuming the state of an interrupted run, here written by `export`,
+    // which other runs refuse to overwrite.
+    let state = dir.join("state.json");
+    run_bot(
+        &dir,
+        &["export", "2025-10", "--output", state.to_str().unwrap()],
+    );
+    assert_eq!(try_run_bot(&dir, &["run", "2025-10"]), Some(2));
+    run_bot(&dir, &["resume", "--on-existing", "duplicate"]);
+    let requests = discord.new_requests();
+    assert_eq!(
+        thread_names(&requests),
+        ["2025-W10 - ⭐", "2025-W10 - 🙃", "2025-W10 - Other"]
+    );
+    assert!(!state.exists());
+
+    drop(discord);
+    std::fs::remove_dir_all(&dir).ok();
+}